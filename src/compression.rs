@@ -0,0 +1,78 @@
+//! Optional gzip/zstd compression of saved entropy files
+//!
+//! Compressed files are detected by their magic bytes (or a `.gz`/`.zst`
+//! extension) when read back, so loading is transparent.
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(data, 0),
+        }
+    }
+    
+    fn decompress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut decompressed = Vec::new();
+                MultiGzDecoder::new(data).read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            }
+            Compression::Zstd => zstd::decode_all(data),
+        }
+    }
+    
+    fn detect(data: &[u8]) -> Option<Compression> {
+        if data.starts_with(&GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if data.starts_with(&ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+    
+    fn from_extension(path: &Path) -> Option<Compression> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a file, transparently decompressing it if it is gzip or zstd compressed
+pub fn read_maybe_compressed(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    let from_extension = Compression::from_extension(path);
+    let Some(compression) = Compression::detect(&data).or(from_extension) else {
+        return Ok(data);
+    };
+    
+    match compression.decompress(&data) {
+        Ok(decompressed) => Ok(decompressed),
+        // Raw entropy can start with a magic number by chance, so only treat a
+        // failed decompression as an error when the extension also says compressed
+        Err(e) if from_extension == Some(compression) => Err(e),
+        Err(_) => Ok(data),
+    }
+}
@@ -0,0 +1,45 @@
+//! Configuration file support
+//!
+//! Defaults are read from `$XDG_CONFIG_HOME/qcoin/config.toml` (or
+//! `~/.config/qcoin/config.toml`) unless `--config <path>` is given.
+//! Command line flags override config values, which override built-in defaults.
+
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::QuantumSource;
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Order in which quantum sources are tried
+    pub sources: Option<Vec<QuantumSource>>,
+    /// Total HTTP request timeout in seconds
+    pub timeout_secs: Option<u64>,
+    /// HTTP connect timeout in seconds
+    pub connect_timeout_secs: Option<u64>,
+    /// Default output file for saved entropy
+    pub output: Option<String>,
+    /// Set to false to disable colored output
+    pub color: Option<bool>,
+    /// Key for ANU's keyed API, used when neither `--anu-api-key` nor `ANU_API_KEY` is set
+    pub anu_api_key: Option<String>,
+}
+
+impl Config {
+    /// Returns the default config file location, if a home directory can be determined
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("qcoin").join("config.toml"))
+    }
+    
+    /// Reads and parses a TOML config file
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+}
@@ -0,0 +1,44 @@
+//! Selectable generators for the seeded flips
+//!
+//! Every generator is seeded from the same 32-byte seed, so runs can compare
+//! how the choice of PRNG affects the aggregate statistics.
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rand_pcg::Pcg64;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Csrng {
+    /// ChaCha12 via rand's `StdRng`
+    #[default]
+    Chacha12,
+    /// ChaCha20
+    Chacha20,
+    /// PCG64 (not cryptographically secure)
+    Pcg,
+    /// Xoshiro256++ (not cryptographically secure)
+    Xoshiro,
+}
+
+impl Csrng {
+    pub fn name(self) -> &'static str {
+        match self {
+            Csrng::Chacha12 => "ChaCha12",
+            Csrng::Chacha20 => "ChaCha20",
+            Csrng::Pcg => "PCG64",
+            Csrng::Xoshiro => "Xoshiro256++",
+        }
+    }
+    
+    /// Creates a generator of this kind from a 32-byte seed
+    pub fn seeded(self, seed: [u8; 32]) -> Box<dyn RngCore + Send> {
+        match self {
+            Csrng::Chacha12 => Box::new(StdRng::from_seed(seed)),
+            Csrng::Chacha20 => Box::new(ChaCha20Rng::from_seed(seed)),
+            Csrng::Pcg => Box::new(Pcg64::from_seed(seed)),
+            Csrng::Xoshiro => Box::new(Xoshiro256PlusPlus::from_seed(seed)),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Detection of providers serving the same entropy twice
+//!
+//! With `--dedupe`, the SHA-256 digest of every buffer fetched from a quantum
+//! provider is kept in a small ring next to the saved entropy file. A buffer
+//! whose digest is already in the ring was served before, which points at a
+//! stale or cached response rather than fresh measurements.
+
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::default_output_path;
+
+/// Number of recent fetches remembered
+const RING_LEN: usize = 32;
+
+/// Returns the file holding the hex digests of recent fetches, newest last
+fn path() -> PathBuf {
+    default_output_path().with_file_name("recent-fetches.json")
+}
+
+/// Records the digest of freshly fetched `bytes` and returns true if it was
+/// already among the recent fetches
+pub fn is_repeat(bytes: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
+    let path = path();
+    let mut ring: VecDeque<String> = match fs::read(&path) {
+        Ok(json) => serde_json::from_slice(&json).unwrap_or_default(),
+        Err(_) => VecDeque::new(),
+    };
+    
+    let digest = hex::encode(Sha256::digest(bytes));
+    if ring.contains(&digest) {
+        return Ok(true);
+    }
+    
+    ring.push_back(digest);
+    while ring.len() > RING_LEN {
+        ring.pop_front();
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec(&ring)?)?;
+    Ok(false)
+}
@@ -0,0 +1,58 @@
+//! Errors returned when fetching or parsing entropy
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CrngError {
+    /// The request could not be sent or the response body could not be read
+    #[error("{0}")]
+    Network(#[from] reqwest::Error),
+    
+    /// The provider answered, but with an error status or a response that can't be trusted
+    #[error("{0}")]
+    BadResponse(String),
+    
+    /// Entropy or a provider response is not valid JSON, hex or base64
+    #[error("{0}")]
+    Decode(String),
+    
+    /// The input or response holds no entropy
+    #[error("{0}")]
+    Empty(&'static str),
+    
+    /// The provider returned fewer bytes than requested
+    #[error("Expected {expected} bytes, got {got}")]
+    ShortResponse { expected: usize, got: usize },
+    
+    /// The saved entropy was already used by an earlier run and reuse is refused
+    #[error("Saved entropy was already used by an earlier run")]
+    AlreadyUsed,
+    
+    /// The saved entropy is older than the caller accepts
+    #[error("Saved entropy is {age}s old, more than the {max_age}s allowed")]
+    TooOld { age: u64, max_age: u64 },
+    
+    /// The provider served the same bytes as a recent fetch
+    #[error("Served the same bytes as a recent fetch")]
+    Repeated,
+    
+    /// Every source in the chain failed
+    #[error("All sources failed")]
+    AllSourcesFailed,
+    
+    /// An entropy file could not be read
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<serde_json::Error> for CrngError {
+    fn from(e: serde_json::Error) -> Self {
+        CrngError::Decode(e.to_string())
+    }
+}
+
+impl From<hex::FromHexError> for CrngError {
+    fn from(e: hex::FromHexError) -> Self {
+        CrngError::Decode(e.to_string())
+    }
+}
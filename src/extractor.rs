@@ -0,0 +1,91 @@
+//! Fair bits extracted from biased entropy
+
+use sha2::{Digest, Sha256};
+
+/// Turns possibly biased or correlated `bytes` into `count` near-uniform bits.
+///
+/// This is a hash-based extractor: output block `i` is the SHA-256 digest of
+/// the whole input followed by `i` as a little-endian `u64`, read most
+/// significant bit first. Unlike von Neumann debiasing it copes with
+/// correlated input and always returns exactly `count` bits, but the result is
+/// only as unpredictable as the input: asking for more bits than the input's
+/// min-entropy stretches it rather than creating new randomness.
+///
+/// ```
+/// use qcoin::extract_fair_bits;
+///
+/// let bits = extract_fair_bits(&[0xff, 0xfe, 0xff, 0xef], 10);
+/// assert_eq!(bits.len(), 10);
+/// assert_eq!(bits, extract_fair_bits(&[0xff, 0xfe, 0xff, 0xef], 10));
+/// ```
+pub fn extract_fair_bits(bytes: &[u8], count: usize) -> Vec<bool> {
+    let input = Sha256::new_with_prefix(bytes);
+    let mut bits = Vec::with_capacity(count);
+    
+    for block in 0u64.. {
+        if bits.len() >= count {
+            break;
+        }
+        let digest = input.clone().chain_update(block.to_le_bytes()).finalize();
+        bits.extend(digest.iter().flat_map(|byte| (0..8).rev().map(move |shift| (byte >> shift) & 1 == 1)));
+    }
+    
+    bits.truncate(count);
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ones_fraction(bits: &[bool]) -> f64 {
+        bits.iter().filter(|&&bit| bit).count() as f64 / bits.len() as f64
+    }
+    
+    /// Bytes whose bits are 1 with probability 7/8, from OR-ing three SHA-256 chains
+    fn skewed_bytes(len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len);
+        let mut block = [0u8; 32];
+        while bytes.len() < len {
+            let a: [u8; 32] = Sha256::digest(block).into();
+            let b: [u8; 32] = Sha256::digest(a).into();
+            let c: [u8; 32] = Sha256::digest(b).into();
+            bytes.extend(a.iter().zip(&b).zip(&c).map(|((a, b), c)| a | b | c));
+            block = c;
+        }
+        bytes.truncate(len);
+        bytes
+    }
+    
+    #[test]
+    fn returns_exactly_the_requested_number_of_bits() {
+        for count in [0, 1, 255, 256, 257, 1000] {
+            assert_eq!(extract_fair_bits(b"entropy", count).len(), count);
+        }
+    }
+    
+    #[test]
+    fn is_deterministic_and_prefix_stable() {
+        let short = extract_fair_bits(b"entropy", 300);
+        let long = extract_fair_bits(b"entropy", 600);
+        assert_eq!(short, extract_fair_bits(b"entropy", 300));
+        assert_eq!(short, long[..300]);
+        assert_ne!(short, extract_fair_bits(b"entropz", 300));
+    }
+    
+    #[test]
+    fn removes_the_bias_of_skewed_input() {
+        let input = skewed_bytes(4096);
+        let input_bits: Vec<bool> = input.iter().flat_map(|byte| (0..8).map(move |shift| (byte >> shift) & 1 == 1)).collect();
+        assert!(ones_fraction(&input_bits) > 0.85);
+        
+        let extracted = extract_fair_bits(&input, 8192);
+        assert!((ones_fraction(&extracted) - 0.5).abs() < 0.03);
+    }
+    
+    #[test]
+    fn constant_input_still_yields_balanced_bits() {
+        let extracted = extract_fair_bits(&[0xff; 64], 8192);
+        assert!((ones_fraction(&extracted) - 0.5).abs() < 0.03);
+    }
+}
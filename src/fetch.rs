@@ -0,0 +1,502 @@
+//! Fetching entropy from the quantum providers
+//!
+//! The one implementation of the ANU QRNG and qrandom.io protocols, used by the
+//! `qcoin` binary's source chain and by library users alike. Nothing is printed,
+//! no entropy is saved to disk and no statistics are recorded: the bytes come
+//! back with their provenance, and each request is logged as a `tracing` event.
+
+use rand::RngCore;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::CrngError;
+use crate::rate_limit::RateLimiter;
+
+/// Public base URL of the ANU QRNG API
+pub const ANU_DEFAULT_URL: &str = "https://qrng.anu.edu.au";
+/// Base URL of ANU's keyed API, which needs an API key
+pub const ANU_API_DEFAULT_URL: &str = "https://api.quantumnumbers.anu.edu.au";
+/// Public base URL of the qrandom.io API
+pub const QRANDOM_DEFAULT_URL: &str = "https://qrandom.io";
+/// Maximum number of bytes the public ANU QRNG API returns per request
+pub const ANU_MAX_CHUNK: usize = 1024;
+/// ANU requests per minute allowed by [`FetchOptions::default`]
+pub const DEFAULT_ANU_RPM: u32 = 30;
+/// Bytes per hex16 block requested from ANU QRNG
+const ANU_HEX_BLOCK_SIZE: usize = 1024;
+/// Most ANU uint8 chunk requests in flight at once
+const ANU_MAX_CONCURRENT: usize = 3;
+/// Hosts a qrandom.io `binaryURL` may point at over HTTPS
+const QRANDOM_BINARY_HOSTS: &[&str] = &["qrandom.io"];
+
+/// Provider metadata: every response field besides the entropy itself
+pub type Metadata = serde_json::Map<String, serde_json::Value>;
+
+/// Quantum entropy providers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Anu,
+    Qrandom,
+}
+
+impl Source {
+    /// Identifier also used for `source` in the binary's JSON output
+    pub fn id(self) -> &'static str {
+        match self {
+            Source::Anu => "anu",
+            Source::Qrandom => "qrandom",
+        }
+    }
+}
+
+/// Where and how [`fetch_entropy`] looks for entropy
+#[derive(Clone, Debug)]
+pub struct FetchOptions {
+    /// Quantum sources, tried in order until one succeeds
+    pub sources: Vec<Source>,
+    /// Timeout of each HTTP request
+    pub timeout: Duration,
+    /// Timeout for establishing each connection
+    pub connect_timeout: Duration,
+    /// Base URL of an ANU QRNG compatible API
+    pub anu_url: String,
+    /// Key for ANU's keyed API. When set, ANU is asked through `anu_api_url`
+    /// instead of `anu_url`.
+    pub anu_api_key: Option<String>,
+    /// Base URL of ANU's keyed API
+    pub anu_api_url: String,
+    /// Bytes per ANU uint8 request. Only a custom `anu_url` backend accepts more
+    /// than [`ANU_MAX_CHUNK`].
+    pub anu_chunk_size: usize,
+    /// Paces ANU fetches, charging each call once however many requests it takes;
+    /// clones of these options share it. `None` leaves them unpaced.
+    pub anu_rate_limit: Option<Arc<RateLimiter>>,
+    /// Base URL of a qrandom.io compatible API
+    pub qrandom_url: String,
+    /// Smallest qrandom.io response accepted when it returns fewer bytes than
+    /// requested. `None` requires all of them.
+    pub min_bytes: Option<usize>,
+    /// Extra root certificates to trust
+    pub ca_certs: Vec<reqwest::Certificate>,
+    /// SHA-256 fingerprints one of which the server's leaf certificate must match.
+    /// Empty accepts any certificate the TLS roots trust.
+    pub cert_pins: Vec<[u8; 32]>,
+    /// Fall back to the operating system's CSRNG when every quantum source fails
+    pub csrng_fallback: bool,
+}
+
+impl Default for FetchOptions {
+    /// The binary's defaults: ANU then qrandom.io, a 30 second request timeout,
+    /// 30 ANU requests per minute and the CSRNG as the last resort
+    fn default() -> Self {
+        FetchOptions {
+            sources: vec![Source::Anu, Source::Qrandom],
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            anu_url: ANU_DEFAULT_URL.to_string(),
+            anu_api_key: None,
+            anu_api_url: ANU_API_DEFAULT_URL.to_string(),
+            anu_chunk_size: ANU_MAX_CHUNK,
+            anu_rate_limit: Some(Arc::new(RateLimiter::per_minute(DEFAULT_ANU_RPM))),
+            qrandom_url: QRANDOM_DEFAULT_URL.to_string(),
+            min_bytes: None,
+            ca_certs: Vec::new(),
+            cert_pins: Vec::new(),
+            csrng_fallback: true,
+        }
+    }
+}
+
+/// Entropy bytes and where they came from
+#[derive(Clone, Debug)]
+pub struct Entropy {
+    pub bytes: Vec<u8>,
+    /// `anu`, `qrandom` or `csrng`
+    pub source: &'static str,
+    /// False when the bytes came from the CSRNG fallback
+    pub is_quantum: bool,
+    /// When the bytes were received
+    pub timestamp: SystemTime,
+    /// The provider's response metadata, from the last response when the bytes
+    /// took several requests
+    pub metadata: Metadata,
+}
+
+impl Entropy {
+    fn new(bytes: Vec<u8>, source: &'static str, is_quantum: bool, metadata: Metadata) -> Self {
+        Entropy {
+            bytes,
+            source,
+            is_quantum,
+            timestamp: SystemTime::now(),
+            metadata,
+        }
+    }
+}
+
+/// Fetches `n` bytes of entropy from the first source in `opts` that delivers
+/// them, falling back to the CSRNG if allowed.
+///
+/// See [`fetch_from`] for how each provider is asked.
+pub fn fetch_entropy(n: usize, opts: &FetchOptions) -> Result<Entropy, CrngError> {
+    let client = client(opts)?;
+    
+    for &source in &opts.sources {
+        match fetch_from(&client, source, n, opts) {
+            Ok(entropy) => return Ok(entropy),
+            Err(e) => tracing::debug!("↪️  {} failed: {}", source.id(), e),
+        }
+    }
+    
+    if opts.csrng_fallback {
+        let mut bytes = vec![0u8; n];
+        rand::rng().fill_bytes(&mut bytes);
+        return Ok(Entropy::new(bytes, "csrng", false, Metadata::new()));
+    }
+    Err(CrngError::AllSourcesFailed)
+}
+
+/// Creates an HTTP client with the timeouts and TLS settings of `opts`
+pub fn client(opts: &FetchOptions) -> Result<Client, CrngError> {
+    let mut builder = Client::builder()
+        .timeout(opts.timeout)
+        .connect_timeout(opts.connect_timeout)
+        .tls_info(!opts.cert_pins.is_empty());
+    for cert in &opts.ca_certs {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+    Ok(builder.build()?)
+}
+
+/// Fetches `n` bytes from a single quantum `source` through `client`.
+///
+/// With an API key ANU is asked through its keyed API in 1024-byte chunks.
+/// Otherwise amounts above `anu_chunk_size` are requested as hex16 blocks,
+/// falling back to uint8 chunks with up to 3 in flight. qrandom.io is asked
+/// through its hex endpoint, then its binary flow, whose `binaryURL` is only
+/// followed to a trusted host.
+pub fn fetch_from(client: &Client, source: Source, n: usize, opts: &FetchOptions) -> Result<Entropy, CrngError> {
+    let fetcher = Fetcher { client, opts };
+    let (bytes, metadata) = match source {
+        Source::Anu => fetcher.anu(n)?,
+        Source::Qrandom => fetcher.qrandom(n)?,
+    };
+    Ok(Entropy::new(bytes, source.id(), true, metadata))
+}
+
+// Fields besides the entropy itself are kept as provenance metadata
+
+#[derive(Deserialize)]
+struct QrandomResponse {
+    #[serde(rename = "binaryURL")]
+    binary_url: String,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
+#[derive(Deserialize)]
+struct QrandomHexResponse {
+    #[serde(alias = "data")]
+    hex: String,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
+/// `data` holds numbers for `type=uint8` and hex strings for `type=hex16`
+#[derive(Deserialize)]
+struct AnuResponse<T> {
+    data: Vec<T>,
+    success: bool,
+    #[serde(rename = "type")]
+    data_type: Option<String>,
+    /// A number from the legacy API, a string from the keyed one
+    length: Option<serde_json::Value>,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
+struct Fetcher<'a> {
+    client: &'a Client,
+    opts: &'a FetchOptions,
+}
+
+impl Fetcher<'_> {
+    /// Asks ANU for `n` bytes. The rate limiter is charged once for the whole
+    /// fetch, so splitting it into chunks or falling back to uint8 requests
+    /// doesn't add waits between requests that belong together.
+    fn anu(&self, n: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        if let Some(limiter) = &self.opts.anu_rate_limit {
+            limiter.wait();
+        }
+        
+        // With an API key the keyed API replaces the legacy endpoint entirely
+        if let Some(key) = &self.opts.anu_api_key {
+            return self.anu_keyed(key, n);
+        }
+        
+        // A single uint8 request already covers small amounts
+        if n <= self.opts.anu_chunk_size {
+            return self.anu_uint8(n);
+        }
+        
+        match self.anu_hex(n) {
+            Ok(fetched) => Ok(fetched),
+            Err(e) => {
+                tracing::debug!("↪️  ANU QRNG hex16 request failed ({}), using uint8 chunks", e);
+                self.anu_uint8(n)
+            }
+        }
+    }
+    
+    /// Fetches entropy as hex16 blocks of `ANU_HEX_BLOCK_SIZE` bytes, so one request
+    /// returns up to 1024 blocks instead of 1024 bytes
+    fn anu_hex(&self, n: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        let mut bytes = Vec::with_capacity(n);
+        let mut metadata = Metadata::new();
+        
+        while bytes.len() < n {
+            let blocks = (n - bytes.len()).div_ceil(ANU_HEX_BLOCK_SIZE).min(ANU_MAX_CHUNK);
+            let url = format!("{}/API/jsonI.php?length={}&type=hex16&size={}", base(&self.opts.anu_url), blocks, ANU_HEX_BLOCK_SIZE);
+            
+            let data: Vec<String>;
+            (data, metadata) = parse_anu_response(&self.get(&url)?)?;
+            if data.len() != blocks {
+                return Err(CrngError::BadResponse(format!("Expected {} hex blocks, got {}", blocks, data.len())));
+            }
+            
+            let before = bytes.len();
+            for block in data {
+                bytes.extend(hex::decode(block.trim()).map_err(|e| CrngError::Decode(format!("Invalid hex block: {}", e)))?);
+            }
+            if bytes.len() == before {
+                return Err(CrngError::Empty("Hex blocks were empty"));
+            }
+        }
+        
+        bytes.truncate(n);
+        Ok((bytes, metadata))
+    }
+    
+    /// Fetches uint8 chunks of `anu_chunk_size` bytes. The client is blocking, so
+    /// chunks are requested from scoped threads in batches of `ANU_MAX_CONCURRENT`,
+    /// and each batch finishes before the next one starts.
+    fn anu_uint8(&self, n: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        let chunk = self.opts.anu_chunk_size.max(1);
+        let chunk_sizes: Vec<usize> = (0..n).step_by(chunk).map(|start| chunk.min(n - start)).collect();
+        let mut bytes = Vec::with_capacity(n);
+        let mut metadata = Metadata::new();
+        
+        // Issue up to ANU_MAX_CONCURRENT chunk requests at once, overlapping the network
+        // waits while keeping the load on the API bounded
+        for batch in chunk_sizes.chunks(ANU_MAX_CONCURRENT) {
+            let results: Vec<Result<(Vec<u8>, Metadata), CrngError>> = thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .map(|&chunk_size| scope.spawn(move || self.anu_chunk(chunk_size)))
+                    .collect();
+                
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                    .collect()
+            });
+            
+            for result in results {
+                let data;
+                (data, metadata) = result?;
+                bytes.extend(data);
+            }
+        }
+        
+        Ok((bytes, metadata))
+    }
+    
+    fn anu_chunk(&self, chunk_size: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        let url = format!("{}/API/jsonI.php?length={}&type=uint8", base(&self.opts.anu_url), chunk_size);
+        let (data, metadata) = parse_anu_response(&self.get(&url)?)?;
+        
+        if data.len() != chunk_size {
+            return Err(CrngError::ShortResponse { expected: chunk_size, got: data.len() });
+        }
+        Ok((data, metadata))
+    }
+    
+    /// Fetches uint8 chunks from ANU's keyed API, which authenticates with an
+    /// `x-api-key` header and answers like the legacy endpoint
+    fn anu_keyed(&self, key: &str, n: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        let mut bytes = Vec::with_capacity(n);
+        let mut metadata = Metadata::new();
+        
+        while bytes.len() < n {
+            let chunk_size = (n - bytes.len()).min(ANU_MAX_CHUNK);
+            let url = format!("{}?length={}&type=uint8", base(&self.opts.anu_api_url), chunk_size);
+            
+            let data: Vec<u8>;
+            (data, metadata) = parse_anu_response(&self.request(self.client.get(&url).header("x-api-key", key), &url)?)?;
+            if data.len() != chunk_size {
+                return Err(CrngError::ShortResponse { expected: chunk_size, got: data.len() });
+            }
+            bytes.extend(data);
+        }
+        
+        Ok((bytes, metadata))
+    }
+    
+    fn qrandom(&self, n: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        // The hex endpoint answers in a single round trip; the binary flow needs two requests
+        let (mut bytes, metadata) = match self.qrandom_hex(n) {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                tracing::debug!("↪️  qrandom.io hex endpoint unavailable ({}), using binary endpoint", e);
+                self.qrandom_binary(n)?
+            }
+        };
+        
+        // The endpoints have been seen returning short responses
+        let min_bytes = self.opts.min_bytes.unwrap_or(n).min(n);
+        if bytes.len() < min_bytes {
+            return Err(CrngError::ShortResponse { expected: n, got: bytes.len() });
+        }
+        bytes.truncate(n);
+        Ok((bytes, metadata))
+    }
+    
+    fn qrandom_hex(&self, n: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        let url = format!("{}/api/random/hex?bytes={}", base(&self.opts.qrandom_url), n);
+        let response: QrandomHexResponse = serde_json::from_slice(&self.get(&url)?)?;
+        Ok((hex::decode(response.hex.trim())?, response.metadata))
+    }
+    
+    fn qrandom_binary(&self, n: usize) -> Result<(Vec<u8>, Metadata), CrngError> {
+        let url = format!("{}/api/random/binary?bytes={}", base(&self.opts.qrandom_url), n);
+        let response: QrandomResponse = serde_json::from_slice(&self.get(&url)?)?;
+        let binary_url = check_binary_url(&response.binary_url, base(&self.opts.qrandom_url)).map_err(CrngError::BadResponse)?;
+        tracing::debug!("🔗 qrandom.io binary data is served from {}", binary_url.host_str().unwrap_or_default());
+        let bytes = self.get(binary_url.as_str())?;
+        
+        let mut metadata = response.metadata;
+        metadata.insert("binaryURL".to_string(), response.binary_url.into());
+        Ok((bytes, metadata))
+    }
+    
+    /// Performs a GET request and returns the response body
+    fn get(&self, url: &str) -> Result<Vec<u8>, CrngError> {
+        self.request(self.client.get(url), url)
+    }
+    
+    /// Sends a GET `request` for `url`, which is only used for logging, so headers
+    /// such as API keys stay out of the log
+    fn request(&self, request: RequestBuilder, url: &str) -> Result<Vec<u8>, CrngError> {
+        tracing::debug!("➡️  GET {}", url);
+        let start = Instant::now();
+        
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!("⬅️  Request to {} failed after {} ms", url, start.elapsed().as_millis());
+                return Err(e.into());
+            }
+        };
+        check_cert_pin(&response, &self.opts.cert_pins)
+            .inspect_err(|e| tracing::debug!("⬅️  {} for {}", e, url))
+            .map_err(CrngError::BadResponse)?;
+        let status = response.status();
+        for (name, value) in response.headers() {
+            tracing::trace!("   {}: {}", name, value.to_str().unwrap_or("<binary>"));
+        }
+        
+        if !status.is_success() {
+            tracing::debug!("⬅️  {} from {} after {} ms", status, url, start.elapsed().as_millis());
+            return Err(CrngError::BadResponse(format!("HTTP {}", status)));
+        }
+        
+        let body = response.bytes()?.to_vec();
+        tracing::debug!("⬅️  {} from {}: {} bytes in {} ms", status, url, body.len(), start.elapsed().as_millis());
+        Ok(body)
+    }
+}
+
+/// A base URL without its trailing slash
+fn base(url: &str) -> &str {
+    url.trim_end_matches('/')
+}
+
+/// Parses an ANU QRNG response, rejecting `success=false`, and returns its data
+/// with the rest of the response as metadata
+fn parse_anu_response<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<(Vec<T>, Metadata), CrngError> {
+    let response: AnuResponse<T> = serde_json::from_slice(body)?;
+    
+    if !response.success {
+        return Err(CrngError::BadResponse("API returned success=false".to_string()));
+    }
+    
+    let mut metadata = response.metadata;
+    metadata.insert("success".to_string(), response.success.into());
+    if let Some(data_type) = response.data_type {
+        metadata.insert("type".to_string(), data_type.into());
+    }
+    if let Some(length) = response.length {
+        metadata.insert("length".to_string(), length);
+    }
+    Ok((response.data, metadata))
+}
+
+/// Rejects responses whose leaf certificate matches none of the `pins`. Plain
+/// HTTP responses have no certificate and are rejected too. No pins accept anything.
+fn check_cert_pin(response: &Response, pins: &[[u8; 32]]) -> Result<(), String> {
+    if pins.is_empty() {
+        return Ok(());
+    }
+    
+    let fingerprint: Option<[u8; 32]> = response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .map(|der| Sha256::digest(der).into());
+    
+    match fingerprint {
+        Some(fingerprint) if pins.contains(&fingerprint) => Ok(()),
+        Some(fingerprint) => Err(format!("Certificate {} is not pinned", hex::encode(fingerprint))),
+        None => Err("No TLS certificate to check against the pinned fingerprints".to_string()),
+    }
+}
+
+/// Only follows a `binaryURL` over HTTPS to an allowlisted host, or to the host of
+/// the configured qrandom.io base URL over its own scheme, so a tampered response
+/// can't point the client at arbitrary hosts
+fn check_binary_url(binary_url: &str, base_url: &str) -> Result<reqwest::Url, String> {
+    let url = reqwest::Url::parse(binary_url).map_err(|e| format!("Invalid binary URL '{}': {}", binary_url, e))?;
+    let host = url.host_str().unwrap_or_default();
+    let base = reqwest::Url::parse(base_url).ok();
+    
+    let same_as_base = base.as_ref().is_some_and(|base| base.host_str() == Some(host) && base.scheme() == url.scheme());
+    let allowlisted = url.scheme() == "https"
+        && QRANDOM_BINARY_HOSTS
+            .iter()
+            .any(|allowed| host == *allowed || host.ends_with(&format!(".{}", allowed)));
+    
+    if same_as_base || allowlisted {
+        Ok(url)
+    } else {
+        Err(format!("Refusing to follow binary URL to untrusted location '{}'", binary_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn binary_url_must_be_https_on_an_allowed_host() {
+        assert!(check_binary_url("https://qrandom.io/data/abc.bin", QRANDOM_DEFAULT_URL).is_ok());
+        assert!(check_binary_url("https://cdn.qrandom.io/abc.bin", QRANDOM_DEFAULT_URL).is_ok());
+        assert!(check_binary_url("http://qrandom.io/abc.bin", QRANDOM_DEFAULT_URL).is_err());
+        assert!(check_binary_url("https://evilqrandom.io/abc.bin", QRANDOM_DEFAULT_URL).is_err());
+        assert!(check_binary_url("https://169.254.169.254/latest", QRANDOM_DEFAULT_URL).is_err());
+        assert!(check_binary_url("http://localhost:8080/abc.bin", "http://localhost:8080").is_ok());
+    }
+}
@@ -0,0 +1,52 @@
+//! Streaming coin flips seeded from an entropy buffer
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
+
+/// An endless iterator of fair coin flips (`true` for heads).
+///
+/// The flipper seeds a `StdRng` with the SHA-256 digest of the whole entropy
+/// buffer, so every input byte contributes to the seed, and then yields one
+/// unbiased bit per call to `next()`. Flips are pulled lazily, so callers never
+/// need to decide up front how many they want.
+///
+/// ```
+/// use qcoin::CoinFlipper;
+///
+/// let flips: Vec<bool> = CoinFlipper::new(b"quantum entropy").take(8).collect();
+/// assert_eq!(flips.len(), 8);
+/// ```
+pub struct CoinFlipper {
+    rng: StdRng,
+    word: u64,
+    bits_left: u32,
+}
+
+impl CoinFlipper {
+    /// Creates a flipper seeded by the SHA-256 digest of `entropy`
+    pub fn new(entropy: &[u8]) -> Self {
+        let seed: [u8; 32] = Sha256::digest(entropy).into();
+        CoinFlipper {
+            rng: StdRng::from_seed(seed),
+            word: 0,
+            bits_left: 0,
+        }
+    }
+}
+
+impl Iterator for CoinFlipper {
+    type Item = bool;
+    
+    fn next(&mut self) -> Option<bool> {
+        if self.bits_left == 0 {
+            self.word = self.rng.next_u64();
+            self.bits_left = u64::BITS;
+        }
+        
+        let bit = self.word & 1 == 1;
+        self.word >>= 1;
+        self.bits_left -= 1;
+        Some(bit)
+    }
+}
@@ -1,5 +1,125 @@
 //! Helper functions for formatting and utilities
 
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(true);
+static SHOW_METADATA: AtomicBool = AtomicBool::new(false);
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables quiet mode for status output
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Returns true when status output should be suppressed
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Enables or disables printing of provider response metadata
+pub fn set_show_metadata(show: bool) {
+    SHOW_METADATA.store(show, Ordering::Relaxed);
+}
+
+/// Returns true when provider response metadata should be printed
+pub fn show_metadata() -> bool {
+    SHOW_METADATA.load(Ordering::Relaxed)
+}
+
+/// Enables or disables ASCII tags in place of emoji prefixes
+pub fn set_ascii(enabled: bool) {
+    ASCII.store(enabled, Ordering::Relaxed);
+}
+
+/// Replaces the emoji that starts `line` with an ASCII tag when `--ascii` is set.
+///
+/// Leading carriage returns and ANSI color codes are kept, so progress lines
+/// and colored warnings are tagged too. Errors become `[ERR]`, warnings
+/// `[WARN]`, checkmarks `[OK]` and any other emoji `[INFO]`.
+pub fn asciify(line: &str) -> Cow<'_, str> {
+    if !ASCII.load(Ordering::Relaxed) {
+        return Cow::Borrowed(line);
+    }
+    
+    let mut start = 0;
+    loop {
+        let rest = &line[start..];
+        if rest.starts_with('\r') {
+            start += 1;
+        } else if rest.starts_with("\x1b[") && let Some(end) = rest.find('m') {
+            start += end + 1;
+        } else {
+            break;
+        }
+    }
+    let rest = &line[start..];
+    let emoji_len = rest.find(|c: char| c.is_ascii()).unwrap_or(rest.len());
+    if emoji_len == 0 {
+        return Cow::Borrowed(line);
+    }
+    
+    let tag = match rest[..emoji_len].trim_end_matches('\u{fe0f}') {
+        "❌" | "💥" => "[ERR]",
+        "⚠" => "[WARN]",
+        "✅" => "[OK]",
+        _ => "[INFO]",
+    };
+    Cow::Owned(format!("{}{} {}", &line[..start], tag, rest[emoji_len..].trim_start_matches(' ')))
+}
+
+/// Enables or disables ANSI color codes in styled output
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+/// Wraps text in the given ANSI style code, or returns it unchanged when color is disabled
+fn paint(text: impl Display, code: &str) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn bold(text: impl Display) -> String {
+    paint(text, "1")
+}
+
+pub fn red(text: impl Display) -> String {
+    paint(text, "31")
+}
+
+pub fn green(text: impl Display) -> String {
+    paint(text, "32")
+}
+
+pub fn yellow(text: impl Display) -> String {
+    paint(text, "33")
+}
+
+pub fn cyan(text: impl Display) -> String {
+    paint(text, "36")
+}
+
+pub fn bold_red(text: impl Display) -> String {
+    paint(text, "1;31")
+}
+
+pub fn bold_green(text: impl Display) -> String {
+    paint(text, "1;32")
+}
+
 /// Formats a number with comma separators for better readability
 /// Example: 1234567 -> "1,234,567"
 pub fn format_number_with_commas(num: u64) -> String {
@@ -9,11 +129,459 @@ pub fn format_number_with_commas(num: u64) -> String {
     
     for (i, &ch) in chars.iter().enumerate() {
         // Add comma every 3 digits from the right
-        if i > 0 && (chars.len() - i) % 3 == 0 {
+        if i > 0 && (chars.len() - i).is_multiple_of(3) {
             result.push(',');
         }
         result.push(ch);
     }
     
     result
-}
\ No newline at end of file
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp, e.g. `2024-03-01T12:00:00Z`
+pub fn format_utc_timestamp(unix_secs: u64) -> String {
+    let (days, secs) = (unix_secs / 86_400, unix_secs % 86_400);
+    
+    // Civil date from days since 1970-01-01 (Howard Hinnant's days_from_civil, inverted)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, secs / 3600, secs % 3600 / 60, secs % 60
+    )
+}
+
+/// Renders a textual progress bar such as `[#######-------]  50% (500/1,000)`
+pub fn progress_bar(done: usize, total: usize, width: usize) -> String {
+    let fraction = if total == 0 { 1.0 } else { done.min(total) as f64 / total as f64 };
+    let filled = (fraction * width as f64).round() as usize;
+    
+    format!(
+        "[{}{}] {:>3}% ({}/{})",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        (fraction * 100.0).round() as u32,
+        format_number_with_commas(done.min(total) as u64),
+        format_number_with_commas(total as u64)
+    )
+}
+
+/// Writes one CSV row per entropy byte with columns `index,byte_value,ones`
+pub fn write_bit_counts_csv(bytes: &[u8], path: &Path) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "index,byte_value,ones")?;
+    
+    for (index, byte) in bytes.iter().enumerate() {
+        writeln!(writer, "{},{},{}", index, byte, byte.count_ones())?;
+    }
+    
+    writer.flush()
+}
+
+/// XORs buffers together into a single buffer of `len` bytes. Buffers longer than
+/// `len` are truncated; shorter ones are zero-padded, which leaves the other
+/// buffers' bytes unchanged at those positions.
+pub fn xor_mix(buffers: &[Vec<u8>], len: usize) -> Vec<u8> {
+    let mut mixed = vec![0u8; len];
+    
+    for buffer in buffers {
+        for (out, &byte) in mixed.iter_mut().zip(buffer) {
+            *out ^= byte;
+        }
+    }
+    
+    mixed
+}
+
+/// Expands bytes into bits, most significant bit first
+pub fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|&byte| (0..8).rev().map(move |shift| (byte >> shift) & 1 == 1))
+        .collect()
+}
+
+/// Packs bits into bytes, most significant bit first. A trailing partial byte is zero-padded.
+pub fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| chunk.iter().enumerate().fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << (7 - i))))
+        .collect()
+}
+
+/// Decodes binary (`bits_per_digit` 1) or octal (3) digits into bytes, most significant
+/// first. Input that doesn't fill whole bytes is zero-padded on the left, so the bytes
+/// keep the number's value: `101` becomes `[0x05]`. Returns `None` on a digit outside the base.
+pub fn decode_radix_digits(digits: &str, bits_per_digit: u32) -> Option<Vec<u8>> {
+    let radix = 1 << bits_per_digit;
+    let mut bits = Vec::with_capacity(digits.len() * bits_per_digit as usize);
+    for digit in digits.chars() {
+        let value = digit.to_digit(radix)?;
+        bits.extend((0..bits_per_digit).rev().map(|shift| (value >> shift) & 1 == 1));
+    }
+    
+    let padding = (8 - bits.len() % 8) % 8;
+    let padded: Vec<bool> = std::iter::repeat_n(false, padding).chain(bits).collect();
+    Some(bits_to_bytes(&padded))
+}
+
+/// Von Neumann extractor: for each pair of bits emits 1 for `10`, 0 for `01`,
+/// and discards `00` and `11`. Removes bias from independent bits at the cost
+/// of keeping at most a quarter of the input on average.
+pub fn von_neumann_debias(bits: &[bool]) -> Vec<bool> {
+    bits.chunks_exact(2)
+        .filter(|pair| pair[0] != pair[1])
+        .map(|pair| pair[0])
+        .collect()
+}
+
+/// Whitens `bytes` by XORing them with a ChaCha20 keystream keyed by their own
+/// SHA-256 digest. The output has the input's length and looks uniform, but it
+/// is a deterministic transform: it spreads the input's entropy, it adds none.
+pub fn chacha_whiten(bytes: &[u8]) -> Vec<u8> {
+    let mut keystream = vec![0u8; bytes.len()];
+    ChaCha20Rng::from_seed(Sha256::digest(bytes).into()).fill_bytes(&mut keystream);
+    bytes.iter().zip(keystream).map(|(byte, key)| byte ^ key).collect()
+}
+
+/// Shannon entropy of the byte distribution, in bits per byte (0.0 to 8.0)
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    
+    let total = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// Pearson's chi-square statistic for a 2x2 table of `[ones, zeros]` tallies,
+/// testing whether both tallies come from the same ones/zeros proportion (1 degree of freedom)
+pub fn chi_square_2x2(first: [u64; 2], second: [u64; 2]) -> f64 {
+    let rows = [first, second].map(|row| row.map(|count| count as f64));
+    let row_totals = rows.map(|row| row[0] + row[1]);
+    let column_totals = [rows[0][0] + rows[1][0], rows[0][1] + rows[1][1]];
+    let total = row_totals[0] + row_totals[1];
+    
+    let mut chi_square = 0.0;
+    for (row, row_total) in rows.iter().zip(row_totals) {
+        for (&observed, column_total) in row.iter().zip(column_totals) {
+            let expected = row_total * column_total / total;
+            if expected > 0.0 {
+                chi_square += (observed - expected).powi(2) / expected;
+            }
+        }
+    }
+    chi_square
+}
+
+/// Serial autocorrelation of the bit stream at `lag`: the Pearson correlation between
+/// each bit and the bit `lag` positions later, from -1.0 to 1.0. Independent bits give
+/// values near zero; periodic patterns push it towards either end. Returns `None` when
+/// there are no bit pairs or either series is constant, where the correlation is undefined.
+pub fn bit_autocorrelation(bytes: &[u8], lag: usize) -> Option<f64> {
+    let bits = bytes_to_bits(bytes);
+    if lag == 0 || bits.len() <= lag {
+        return None;
+    }
+    
+    let pairs = bits.len() - lag;
+    let (leading, trailing) = (&bits[..pairs], &bits[lag..]);
+    let mean = |series: &[bool]| series.iter().filter(|&&bit| bit).count() as f64 / pairs as f64;
+    let (leading_mean, trailing_mean) = (mean(leading), mean(trailing));
+    
+    let (mut covariance, mut leading_variance, mut trailing_variance) = (0.0, 0.0, 0.0);
+    for (&x, &y) in leading.iter().zip(trailing) {
+        let (dx, dy) = (x as u8 as f64 - leading_mean, y as u8 as f64 - trailing_mean);
+        covariance += dx * dy;
+        leading_variance += dx * dx;
+        trailing_variance += dy * dy;
+    }
+    
+    if leading_variance == 0.0 || trailing_variance == 0.0 {
+        return None;
+    }
+    Some(covariance / (leading_variance * trailing_variance).sqrt())
+}
+
+/// Pearson's chi-square goodness-of-fit statistic for `observed` counts against the
+/// `expected` probability of each category, with `categories - 1` degrees of freedom
+pub fn chi_square_goodness_of_fit(observed: &[u64], expected: &[f64]) -> f64 {
+    let total: u64 = observed.iter().sum();
+    observed
+        .iter()
+        .zip(expected)
+        .map(|(&count, &probability)| {
+            let expected_count = total as f64 * probability;
+            if expected_count > 0.0 {
+                (count as f64 - expected_count).powi(2) / expected_count
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+/// Upper-tail p-value of a chi-square statistic with one degree of freedom,
+/// `erfc(sqrt(x / 2))`, accurate to about 1e-7
+pub fn chi_square_p_value_1df(chi_square: f64) -> f64 {
+    if chi_square <= 0.0 {
+        return 1.0;
+    }
+    
+    // Abramowitz and Stegun 7.1.26
+    let x = (chi_square / 2.0).sqrt();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    poly * (-x * x).exp()
+}
+
+/// Summarizes entropy health as a single score from 0 (broken) to 100 (healthy).
+///
+/// The score blends three simple tests, each scored from 0.0 to 1.0:
+/// - Shannon entropy (40 points): bits per byte relative to the maximum a buffer
+///   of this size can reach, `log2(min(len, 256))`.
+/// - Monobit (30 points): full marks while the count of one bits is within 2
+///   standard deviations of half the bits, falling linearly to zero at 6.
+/// - Longest run (30 points): full marks while the longest run of identical bits
+///   is at most `log2(bits) + 3`, falling linearly to zero at twice that length.
+///
+/// These are quick sanity checks, not a substitute for a statistical test suite.
+pub fn quality_score(bytes: &[u8]) -> u8 {
+    if bytes.is_empty() {
+        return 0;
+    }
+    
+    let max_entropy = (bytes.len().min(256) as f64).log2();
+    let entropy_score = if max_entropy > 0.0 {
+        (shannon_entropy(bytes) / max_entropy).min(1.0)
+    } else {
+        1.0
+    };
+    
+    let bits = bytes.len() as f64 * 8.0;
+    let ones: u64 = bytes.iter().map(|byte| byte.count_ones() as u64).sum();
+    let z = (ones as f64 - bits / 2.0).abs() / (bits.sqrt() / 2.0);
+    let monobit_score = (1.0 - (z - 2.0) / 4.0).clamp(0.0, 1.0);
+    
+    let expected_run = bits.log2() + 3.0;
+    let (ones_run, zeros_run) = longest_runs(bytes);
+    let run = ones_run.max(zeros_run) as f64;
+    let run_score = (1.0 - (run - expected_run) / expected_run).clamp(0.0, 1.0);
+    
+    (40.0 * entropy_score + 30.0 * monobit_score + 30.0 * run_score).round() as u8
+}
+
+/// Lengths of the longest runs of 1-bits and of 0-bits, following the bits across
+/// byte boundaries, most significant bit first
+pub fn longest_runs(bytes: &[u8]) -> (usize, usize) {
+    let mut longest = [0usize; 2];
+    let mut current = 0;
+    let mut previous = None;
+    
+    for bit in bytes_to_bits(bytes) {
+        if previous == Some(bit) {
+            current += 1;
+        } else {
+            current = 1;
+            previous = Some(bit);
+        }
+        longest[bit as usize] = longest[bit as usize].max(current);
+    }
+    
+    (longest[1], longest[0])
+}
+
+/// Counts byte values into `buckets` equally sized ranges across 0-255
+pub fn byte_histogram(bytes: &[u8], buckets: usize) -> Vec<usize> {
+    let mut counts = vec![0usize; buckets];
+    
+    for &byte in bytes {
+        counts[byte as usize * buckets / 256] += 1;
+    }
+    
+    counts
+}
+
+/// Renders a horizontal bar of block characters, scaled so `max` fills `width` cells
+pub fn histogram_bar(value: usize, max: usize, width: usize) -> String {
+    const PARTIAL_BLOCKS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    
+    if max == 0 {
+        return String::new();
+    }
+    
+    // Work in eighths of a cell so partial blocks smooth out the bar ends
+    let eighths = value * width * 8 / max;
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(PARTIAL_BLOCKS[eighths % 8]);
+    }
+    
+    bar
+}
+
+/// Returns the terminal width from `COLUMNS`, defaulting to 80
+pub fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(80)
+}
+
+/// Returns true if the input contains characters that appear in base64 but never in hex
+pub fn looks_like_base64(input: &str) -> bool {
+    input.contains(['+', '/', '=', '-', '_'])
+}
+
+/// Decodes base64 in either the standard or URL-safe alphabet, with or without padding
+pub fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(input).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn ascii_mode_tags_emoji_prefixes() {
+        set_ascii(true);
+        assert_eq!(asciify("❌ ANU QRNG: HTTP 500"), "[ERR] ANU QRNG: HTTP 500");
+        assert_eq!(asciify("⚠️  Low entropy"), "[WARN] Low entropy");
+        assert_eq!(asciify("✅ Saved"), "[OK] Saved");
+        assert_eq!(asciify("\r⏳ [###]"), "\r[INFO] [###]");
+        assert_eq!(asciify("\x1b[33m⚠️  Warning\x1b[0m"), "\x1b[33m[WARN] Warning\x1b[0m");
+        assert_eq!(asciify("   Heads: 3"), "   Heads: 3");
+        set_ascii(false);
+        assert_eq!(asciify("✅ Saved"), "✅ Saved");
+    }
+    
+    #[test]
+    fn binary_and_octal_digits_decode_to_left_padded_bytes() {
+        assert_eq!(decode_radix_digits("1111000000001111", 1), Some(vec![0xf0, 0x0f]));
+        assert_eq!(decode_radix_digits("101", 1), Some(vec![0x05]));
+        assert_eq!(decode_radix_digits("777", 3), Some(vec![0x01, 0xff]));
+        assert_eq!(decode_radix_digits("377", 3), Some(vec![0x00, 0xff]));
+        assert_eq!(decode_radix_digits("102", 1), None);
+        assert_eq!(decode_radix_digits("78", 3), None);
+    }
+    
+    #[test]
+    fn whitening_balances_constant_input_deterministically() {
+        let input = [0xff; 4096];
+        let whitened = chacha_whiten(&input);
+        assert_eq!(whitened.len(), input.len());
+        assert_eq!(whitened, chacha_whiten(&input));
+        
+        let ones: u32 = whitened.iter().map(|byte| byte.count_ones()).sum();
+        assert!((ones as f64 / (input.len() * 8) as f64 - 0.5).abs() < 0.01);
+        assert_ne!(chacha_whiten(&[0xfe; 4096]), whitened);
+    }
+    
+    #[test]
+    fn goodness_of_fit_against_a_fair_coin() {
+        assert_eq!(chi_square_goodness_of_fit(&[50, 50], &[0.5, 0.5]), 0.0);
+        assert!((chi_square_goodness_of_fit(&[60, 40], &[0.5, 0.5]) - 4.0).abs() < 1e-12);
+        assert!((chi_square_goodness_of_fit(&[40, 60], &[0.3, 0.7]) - 100.0 / 21.0).abs() < 1e-12);
+    }
+    
+    #[test]
+    fn p_values_match_chi_square_tables() {
+        assert_eq!(chi_square_p_value_1df(0.0), 1.0);
+        assert!((chi_square_p_value_1df(3.841) - 0.05).abs() < 1e-4);
+        assert!((chi_square_p_value_1df(6.635) - 0.01).abs() < 1e-4);
+        assert!((chi_square_p_value_1df(4.0) - 0.0455).abs() < 1e-4);
+        assert!(chi_square_p_value_1df(100.0) < 1e-10);
+    }
+    
+    #[test]
+    fn timestamps_are_formatted_in_utc() {
+        assert_eq!(format_utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc_timestamp(951_825_600), "2000-02-29T12:00:00Z");
+        assert_eq!(format_utc_timestamp(1_709_251_199), "2024-02-29T23:59:59Z");
+    }
+    
+    #[test]
+    fn alternating_bits_are_perfectly_anticorrelated_at_lag_one() {
+        let bytes = [0x55; 64];
+        assert!((bit_autocorrelation(&bytes, 1).unwrap() + 1.0).abs() < 1e-12);
+        assert!((bit_autocorrelation(&bytes, 2).unwrap() - 1.0).abs() < 1e-12);
+    }
+    
+    #[test]
+    fn nibble_blocks_correlate_at_short_lags() {
+        // 11110000 repeated: neighbours match 6 times in 8, bits 4 apart never do
+        let bytes = [0xf0; 64];
+        assert!((bit_autocorrelation(&bytes, 1).unwrap() - 0.5).abs() < 0.01);
+        assert!((bit_autocorrelation(&bytes, 4).unwrap() + 1.0).abs() < 1e-12);
+        assert!((bit_autocorrelation(&bytes, 8).unwrap() - 1.0).abs() < 1e-12);
+    }
+    
+    #[test]
+    fn autocorrelation_is_undefined_for_constant_or_short_input() {
+        assert_eq!(bit_autocorrelation(&[0xff; 16], 1), None);
+        assert_eq!(bit_autocorrelation(&[0xa5], 8), None);
+        assert_eq!(bit_autocorrelation(&[0xa5], 0), None);
+    }
+    
+    #[test]
+    fn well_mixed_bytes_have_little_autocorrelation() {
+        // Bytes of a SHA-256 chain stand in for random data
+        let mut bytes = Vec::new();
+        let mut block = [0u8; 32];
+        for _ in 0..256 {
+            block = Sha256::digest(block).into();
+            bytes.extend_from_slice(&block);
+        }
+        assert!(bit_autocorrelation(&bytes, 1).unwrap().abs() < 0.05);
+    }
+    
+    #[test]
+    fn von_neumann_keeps_only_unequal_pairs() {
+        assert_eq!(von_neumann_debias(&[false, true]), vec![false]);
+        assert_eq!(von_neumann_debias(&[true, false]), vec![true]);
+        assert!(von_neumann_debias(&[false, false, true, true]).is_empty());
+        assert_eq!(von_neumann_debias(&[true, false, true, true, false, true, false, false]), vec![true, false]);
+        // A trailing unpaired bit is dropped
+        assert_eq!(von_neumann_debias(&[false, true, true]), vec![false]);
+        assert!(von_neumann_debias(&[true]).is_empty());
+    }
+    
+    #[test]
+    fn constant_input_gets_the_worst_quality_score() {
+        assert_eq!(quality_score(&[0x00; 64]), 0);
+        assert_eq!(quality_score(&[]), 0);
+        let varied: Vec<u8> = (0..=255).collect();
+        assert!(quality_score(&varied) > quality_score(&[0x00; 256]));
+    }
+    
+    #[test]
+    fn longest_runs_follow_bits_across_bytes() {
+        assert_eq!(longest_runs(&[0x55; 8]), (1, 1));
+        assert_eq!(longest_runs(&[0xaa, 0xaa]), (1, 1));
+        assert_eq!(longest_runs(&[0x0f, 0xf0]), (8, 4));
+        // 15 zeros, then two ones straddling the second byte boundary
+        assert_eq!(longest_runs(&[0x00, 0x01, 0x80]), (2, 15));
+        assert_eq!(longest_runs(&[]), (0, 0));
+    }
+}
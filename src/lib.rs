@@ -0,0 +1,15 @@
+//! Quantum coin toss library
+//!
+//! Reusable pieces of the `qcoin` binary for fetching entropy into memory, turning an
+//! entropy buffer into coin flips, and the errors reported while obtaining that entropy.
+
+pub mod error;
+pub mod extractor;
+pub mod fetch;
+pub mod flipper;
+pub mod rate_limit;
+
+pub use error::CrngError;
+pub use extractor::extract_fair_bits;
+pub use fetch::{Entropy, FetchOptions, fetch_entropy};
+pub use flipper::CoinFlipper;
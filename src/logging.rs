@@ -0,0 +1,112 @@
+//! Diagnostic logging through `tracing`
+//!
+//! Status lines are info events with the [`STATUS_TARGET`] target, shown by
+//! default; `-v` adds qcoin's debug events and `-vv` its trace events, and
+//! `RUST_LOG` replaces all of that with any `EnvFilter` directive. By default
+//! each event is printed as its bare message, status lines to stdout and
+//! diagnostics to stderr, with `--ascii` tags and an `error` field appended in
+//! red, while `--log-format json` prints one JSON object per event to stderr with
+//! its span fields. Events therefore carry plain text, and coloring is left to
+//! the formatter.
+
+use std::fmt::{self, Write as _};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::filter::{FilterExt, filter_fn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Formats for status and diagnostic log events
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// The message alone, as qcoin has always printed it
+    #[default]
+    Pretty,
+    /// One JSON object per event, with its level, fields and spans
+    Json,
+}
+
+/// Target of the status line events logged by `status!`
+pub const STATUS_TARGET: &str = "qcoin::status";
+
+/// Installs the global subscriber for the given `-v` count and format
+pub fn init(verbosity: u8, format: LogFormat) {
+    let level = match verbosity {
+        0 => "warn",
+        1 => "debug",
+        _ => "trace",
+    };
+    let events = || EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(format!("warn,qcoin={},{}=info", level, STATUS_TARGET)));
+    let is_status = |metadata: &Metadata<'_>| metadata.target() == STATUS_TARGET;
+    // Spans are always recorded, so a warning still carries the fetch it happened in
+    let is_span = |metadata: &Metadata<'_>| metadata.is_span();
+    
+    let _ = match format {
+        LogFormat::Pretty => tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(std::io::stdout)
+                    .event_format(MessageOnly)
+                    .with_filter(events().and(filter_fn(is_status))),
+            )
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(std::io::stderr)
+                    .event_format(MessageOnly)
+                    .with_filter(events().and(filter_fn(move |metadata| !is_status(metadata))).or(filter_fn(is_span))),
+            )
+            .try_init(),
+        LogFormat::Json => tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(std::io::stderr)
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_filter(events().or(filter_fn(is_span))),
+            )
+            .try_init(),
+    };
+}
+
+/// Prints only an event's message and its `error` field, leaving out its level,
+/// target and other fields
+struct MessageOnly;
+
+impl<S, N> FormatEvent<S, N> for MessageOnly
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let mut line = visitor.message;
+        if let Some(error) = visitor.error {
+            let _ = write!(line, ": {}", crate::helpers::red(error));
+        }
+        writeln!(writer, "{}", crate::helpers::asciify(&line))
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    error: Option<String>,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.message, "{:?}", value);
+            }
+            "error" => self.error = Some(format!("{:?}", value)),
+            _ => {}
+        }
+    }
+}
@@ -1,28 +1,429 @@
-use rand::{RngCore, SeedableRng};
-use rand::rngs::StdRng;
+use rand::RngCore;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
 use rayon::prelude::*;
 use reqwest::blocking::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::Path;
-use std::time::Duration;
-use clap::Parser;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use clap::parser::ValueSource;
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 
+/// Prints a line to stdout with `--ascii` tags in place of emoji prefixes
+macro_rules! out {
+    () => {
+        std::println!()
+    };
+    ($($arg:tt)*) => {
+        std::println!("{}", helpers::asciify(&format!($($arg)*)))
+    };
+}
+
+/// Prints a line to stderr with `--ascii` tags in place of emoji prefixes
+macro_rules! err_out {
+    () => {
+        std::eprintln!()
+    };
+    ($($arg:tt)*) => {
+        std::eprintln!("{}", helpers::asciify(&format!($($arg)*)))
+    };
+}
+
+/// Logs a status line as an info event, printed to stdout unless `--quiet` was
+/// given or `RUST_LOG` filters it out
+macro_rules! status {
+    () => {
+        status!("")
+    };
+    ($($arg:tt)*) => {
+        if !helpers::is_quiet() {
+            tracing::info!(target: crate::logging::STATUS_TARGET, $($arg)*);
+        }
+    };
+}
+
+/// Logs a diagnostic event, shown on stderr with `-v` for level 1 (debug) and
+/// `-vv` for level 2 (trace), or as `RUST_LOG` selects
+macro_rules! verbose {
+    (1, $($arg:tt)*) => {
+        tracing::debug!($($arg)*)
+    };
+    (2, $($arg:tt)*) => {
+        tracing::trace!($($arg)*)
+    };
+}
+
+mod compression;
+mod config;
+mod csrng;
+mod dedupe;
 mod helpers;
-use helpers::format_number_with_commas;
+mod logging;
+mod metrics;
+mod pick;
+mod pool;
+mod session;
+mod source;
+mod stats;
+mod timings;
+mod tui;
+use compression::{Compression, read_maybe_compressed};
+use config::Config;
+use logging::LogFormat;
+use pick::Picker;
+use pool::EntropyPool;
+use qcoin::{CrngError, extract_fair_bits};
+use qcoin::FetchOptions;
+use qcoin::fetch::{ANU_API_DEFAULT_URL, ANU_DEFAULT_URL, ANU_MAX_CHUNK, QRANDOM_DEFAULT_URL};
+use qcoin::rate_limit::RateLimiter;
+use source::EntropySource;
+use csrng::Csrng;
+use timings::Phase;
+use helpers::{bit_autocorrelation, bits_to_bytes, bold, bold_green, bold_red, byte_histogram, bytes_to_bits, chacha_whiten, chi_square_2x2, chi_square_goodness_of_fit, chi_square_p_value_1df, cyan, decode_base64, decode_radix_digits, format_number_with_commas, format_utc_timestamp, green, histogram_bar, longest_runs, looks_like_base64, progress_bar, quality_score, red, shannon_entropy, terminal_width, von_neumann_debias, write_bit_counts_csv, yellow};
 
+/// File name of the saved entropy pool
 const DEFAULT_OUTPUT_FILE: &str = "qrandom.bytes";
+/// Minimum number of CSRNG flips before a progress bar is shown
+const PROGRESS_MIN_FLIPS: usize = 1000;
+/// Seed length required by the seeded generators
+const SEED_LEN: usize = 32;
+/// CSRNG bytes whose bits decide one flip, unless --flip-unit says otherwise
+const DEFAULT_FLIP_UNIT: usize = 1024;
+const WEIGHTED_BYTES_PER_FLIP: usize = 7;
+/// Fraction of the maximum possible Shannon entropy below which entropy is flagged as low
+const LOW_ENTROPY_FRACTION: f64 = 0.5;
+/// Entropy bits per flip below which the flips can't all be independent, since a fair flip needs one bit
+const MIN_BITS_PER_FLIP: f64 = 1.0;
+/// Number of runs' worth of entropy held by --entropy-pool
+const POOL_RUNS: usize = 4;
+/// Most CSRNG bytes `qcoin stream` writes at once while the next fetch runs
+const STREAM_BLOCK: usize = 4096;
+/// Version of the JSON result schema, bumped when fields are renamed, removed or change meaning.
+/// Adding fields is not a breaking change.
+const JSON_SCHEMA_VERSION: u32 = 1;
+/// Chi-square value with one degree of freedom above which a difference is significant at 5%
+const CHI_SQUARE_CRITICAL_1DF: f64 = 3.841;
+/// Encodings for saved entropy files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Encoding {
+    Hex,
+    Base64,
+    Binary,
+}
+
+impl Encoding {
+    fn encode(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Hex => hex::encode(bytes).into_bytes(),
+            Encoding::Base64 => BASE64_STANDARD.encode(bytes).into_bytes(),
+            Encoding::Binary => bytes.to_vec(),
+        }
+    }
+    
+    /// Picks the encoding a file extension implies, looking past a `.gz` or `.zst`
+    /// suffix: `.bin` is raw, `.hex` and `.txt` are hex and `.b64` is base64
+    fn from_path(path: &str) -> Option<Encoding> {
+        let mut path = Path::new(path);
+        if matches!(path.extension().and_then(|extension| extension.to_str()), Some("gz" | "zst")) {
+            path = Path::new(path.file_stem()?);
+        }
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "bin" => Some(Encoding::Binary),
+            "hex" | "txt" => Some(Encoding::Hex),
+            "b64" => Some(Encoding::Base64),
+            _ => None,
+        }
+    }
+}
+
+/// How entropy is written to files
+#[derive(Clone, Copy)]
+struct SaveOptions {
+    encoding: Encoding,
+    compression: Option<Compression>,
+    append: bool,
+}
+
+/// Quantum entropy providers, in the order they are tried by default
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuantumSource {
+    Anu,
+    Qrandom,
+}
+
+/// Set while the CSRNG flip loop runs, so Ctrl-C stops it early instead of exiting
+static FLIPPING: AtomicBool = AtomicBool::new(false);
+/// Set when Ctrl-C interrupted the flip loop; the tally printed afterwards is partial
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Set while --watch or `qcoin stream` runs, so Ctrl-C ends it with a summary instead of exiting
+static WATCHING: AtomicBool = AtomicBool::new(false);
+/// Set when --format asks for JSON, so fatal errors are reported as JSON too
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// How the quantum sources are reached, set once at startup from the URL, TLS,
+/// rate limit and chunk size options
+static FETCH_OPTIONS: OnceLock<FetchOptions> = OnceLock::new();
+
+/// Returns the options the quantum sources are fetched with
+fn fetch_options() -> &'static FetchOptions {
+    FETCH_OPTIONS.get_or_init(FetchOptions::default)
+}
+
+impl QuantumSource {
+    const ALL: [QuantumSource; 2] = [QuantumSource::Anu, QuantumSource::Qrandom];
+    
+
+    fn name(self) -> &'static str {
+        match self {
+            QuantumSource::Anu => "ANU QRNG",
+            QuantumSource::Qrandom => "qrandom.io",
+        }
+    }
+    
+    /// Base URL requests are made against, without a trailing slash
+    fn base_url(self) -> &'static str {
+        let options = fetch_options();
+        match self {
+            QuantumSource::Anu => options.anu_url.trim_end_matches('/'),
+            QuantumSource::Qrandom => options.qrandom_url.trim_end_matches('/'),
+        }
+    }
+    
+    /// The library's name for this provider
+    fn provider(self) -> qcoin::fetch::Source {
+        match self {
+            QuantumSource::Anu => qcoin::fetch::Source::Anu,
+            QuantumSource::Qrandom => qcoin::fetch::Source::Qrandom,
+        }
+    }
+    
+    /// Identifier used in config files and the statistics file
+    fn id(self) -> &'static str {
+        EntropyOrigin::from(self).as_str()
+    }
+    
+    /// Fetches `num_bytes` through the library's fetcher, recording the attempt in
+    /// the source statistics and printing the response metadata for --show-metadata
+    fn fetch(self, client: &Client, num_bytes: usize, options: &FetchOptions) -> Result<Vec<u8>, CrngError> {
+        let _request = tracing::info_span!("provider", source = self.name(), bytes = num_bytes).entered();
+        let start = Instant::now();
+        let result = qcoin::fetch::fetch_from(client, self.provider(), num_bytes, options);
+        stats::record(self.id(), result.is_ok(), start.elapsed());
+        let entropy = result?;
+        print_metadata(self.name(), &entropy.metadata);
+        Ok(entropy.bytes)
+    }
+}
+
+/// Where a run's entropy came from, as reported by machine-readable output formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EntropyOrigin {
+    Anu,
+    Qrandom,
+    Mixed,
+    Saved,
+    Csrng,
+    Hex,
+    Base64,
+    File,
+}
+
+impl EntropyOrigin {
+    fn is_quantum(self) -> bool {
+        matches!(self, EntropyOrigin::Anu | EntropyOrigin::Qrandom | EntropyOrigin::Mixed | EntropyOrigin::Saved)
+    }
+    
+    fn as_str(self) -> &'static str {
+        match self {
+            EntropyOrigin::Anu => "anu",
+            EntropyOrigin::Qrandom => "qrandom",
+            EntropyOrigin::Mixed => "mixed",
+            EntropyOrigin::Saved => "saved",
+            EntropyOrigin::Csrng => "csrng",
+            EntropyOrigin::Hex => "hex",
+            EntropyOrigin::Base64 => "base64",
+            EntropyOrigin::File => "file",
+        }
+    }
+}
+
+impl From<QuantumSource> for EntropyOrigin {
+    fn from(source: QuantumSource) -> Self {
+        match source {
+            QuantumSource::Anu => EntropyOrigin::Anu,
+            QuantumSource::Qrandom => EntropyOrigin::Qrandom,
+        }
+    }
+}
+
+/// Formats for the final result
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Human-readable status and result lines
+    Text,
+    /// A JSON object, or an array of objects with --repeat
+    Json,
+    /// One compact JSON object per line, printed as soon as each run finishes
+    Jsonl,
+    /// One tab-separated `ones zeros ratio outcome source` line per run
+    Tsv,
+}
 
-#[derive(Deserialize)]
-struct QRandomResponse {
-    #[serde(rename = "binaryURL")]
-    binary_url: String,
+/// Names printed for the two outcomes instead of YES and NO, from --labels
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Labels {
+    heads: String,
+    tails: String,
 }
 
-#[derive(Deserialize)]
-struct AnuQrngResponse {
-    data: Vec<u8>,
-    success: bool,
+/// Characters used to print the flip sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum SequenceStyle {
+    /// `H` for heads, `T` for tails
+    Ht,
+    /// `1` for heads, `0` for tails
+    Binary,
+}
+
+/// What to do when the saved entropy file holds entropy an earlier run already used
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum SavedReuse {
+    /// Reuse it without comment
+    Allow,
+    /// Reuse it, warning that it is not fresh
+    Warn,
+    /// Skip it and fall through to the next source
+    Refuse,
+}
+
+/// What --dedupe does when a provider serves entropy identical to a recent fetch
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum DedupeAction {
+    /// Use it, warning that it is not fresh
+    Warn,
+    /// Fetch again, falling through to the next source if the repeat persists
+    Refetch,
+}
+
+/// Where the bytes command writes the fetched bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum BytesFormat {
+    /// Save them to the `-o` file in the `--encoding` format
+    File,
+    /// Print only their hex string to stdout, with no status output
+    RawHex,
+}
+
+/// How entropy shorter than the 32-byte seed is extended into a full seed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum SeedPadding {
+    /// Repeat the entropy bytes; discouraged, as the seed then contains the same bytes several times
+    Cycle,
+    /// Fill the rest of the seed with zero bytes
+    Zero,
+    /// Use the SHA-256 digest of the entropy, which spreads every input bit over the whole seed
+    #[default]
+    Hash,
+}
+
+impl SeedPadding {
+    fn as_str(self) -> &'static str {
+        match self {
+            SeedPadding::Cycle => "cycle",
+            SeedPadding::Zero => "zero",
+            SeedPadding::Hash => "hash",
+        }
+    }
+}
+
+/// Result of one flip experiment, shared by the JSON and TSV output formats
+#[derive(Serialize)]
+struct FlipResult {
+    schema_version: u32,
+    /// Version of qcoin that produced the result
+    version: &'static str,
+    ones: u64,
+    zeros: u64,
+    ratio: f64,
+    outcome: &'static str,
+    /// Whether the outcome is YES, regardless of --labels
+    yes: bool,
+    /// The --labels name of the outcome
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    source: EntropyOrigin,
+    /// SHA-256 of the entropy buffer, with --hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entropy_sha256: Option<String>,
+    /// How a short seed was padded, when the entropy was shorter than the seed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed_padding: Option<SeedPadding>,
+    /// Entropy bytes that decided the flips, read directly or through the seed
+    entropy_used: u64,
+    /// `entropy_used` in bits divided by the number of flips
+    entropy_bits_per_flip: f64,
+    /// When the flip happened, with --watch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+}
+
+impl FlipResult {
+    fn new(ones: u64, zeros: u64, source: EntropyOrigin) -> Self {
+        let total = ones + zeros;
+        FlipResult {
+            schema_version: JSON_SCHEMA_VERSION,
+            version: env!("CARGO_PKG_VERSION"),
+            ones,
+            zeros,
+            ratio: if total == 0 { 0.0 } else { ones as f64 / total as f64 },
+            outcome: if ones > zeros { "YES" } else { "NO" },
+            yes: ones > zeros,
+            label: None,
+            source,
+            entropy_sha256: None,
+            seed_padding: None,
+            entropy_used: 0,
+            entropy_bits_per_flip: 0.0,
+            timestamp: None,
+        }
+    }
+    
+    fn is_yes(&self) -> bool {
+        self.ones > self.zeros
+    }
+    
+    /// Formats the result as one line of the --result-file audit trail
+    fn to_record(&self, format: OutputFormat, timestamp: &str) -> String {
+        match format {
+            OutputFormat::Text => format!(
+                "{} {} ones, {} zeros, ratio {:.4}, outcome {}, source {}",
+                timestamp, self.ones, self.zeros, self.ratio, self.outcome, self.source.as_str()
+            ),
+            OutputFormat::Json | OutputFormat::Jsonl => {
+                let mut record = serde_json::to_value(self).expect("result serializes");
+                record["timestamp"] = serde_json::Value::from(timestamp);
+                record.to_string()
+            }
+            OutputFormat::Tsv => format!("{}\t{}", timestamp, self.to_tsv()),
+        }
+    }
+    
+    fn to_tsv(&self) -> String {
+        let line = format!("{}\t{}\t{}\t{}\t{}", self.ones, self.zeros, self.ratio, self.outcome, self.source.as_str());
+        match &self.entropy_sha256 {
+            Some(hash) => format!("{}\t{}", line, hash),
+            None => line,
+        }
+    }
 }
 
 /// Quantum Coin Toss - Generate truly random coin flips using quantum entropy
@@ -31,202 +432,2386 @@ struct AnuQrngResponse {
 #[command(about = "A quantum random number generator for coin tosses")]
 #[command(long_about = "Generate truly random coin flips using quantum entropy sources like ANU QRNG and qrandom.io. Fallback to cryptographically secure RNG when quantum sources are unavailable.")]
 #[command(version)]
+#[command(group(ArgGroup::new("input").args(["source_file", "hex_string", "base64_string"])))]
 struct Args {
-    /// Number of coin flips to perform
-    #[arg(short = 'n', long = "number", value_name = "NUM_FLIPS", default_value = "1")]
+    #[command(subcommand)]
+    command: Option<Command>,
+    
+    /// Number of coin flips to perform, e.g. 500, 10k, 1M or 1_000_000
+    #[arg(short = 'n', long = "number", value_name = "NUM_FLIPS", default_value = "1", value_parser = parse_count)]
     num_flips: usize,
     
-    /// Output file for quantum entropy bytes (hex format)
-    #[arg(short = 'o', long = "output", value_name = "FILE", default_value = DEFAULT_OUTPUT_FILE)]
-    output_file: String,
+    /// Number of entropy bytes to fetch from quantum sources
+    #[arg(long = "entropy-bytes", value_name = "NUM_BYTES", default_value = "1024")]
+    entropy_bytes: usize,
+    
+    /// Output file for quantum entropy bytes. Repeat to write several files, each
+    /// encoded according to its extension unless --encoding is given.
+    /// [default: qrandom.bytes in the platform cache directory, e.g. ~/.cache/qcoin]
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    output_file: Vec<String>,
+    
+    /// Don't save fetched or input entropy to a file. An existing saved file is
+    /// still reused if every quantum source fails.
+    #[arg(long = "no-save", conflicts_with_all = ["output_file", "append"])]
+    no_save: bool,
     
     /// Source file to use as entropy source instead of quantum sources.
     /// File can contain hex string (e.g., "abc123", "0xabc123") or raw binary data.
-    /// Hex strings are automatically detected and decoded.
-    #[arg(short = 's', long = "source", value_name = "FILE", conflicts_with = "output_file")]
-    source_file: Option<String>,
+    /// Hex strings are automatically detected and decoded unless --raw is given.
+    /// Repeat to concatenate several files in order, each detected independently.
+    #[arg(short = 's', long = "source", value_name = "FILE", group = "input", conflicts_with = "output_file")]
+    source_file: Vec<String>,
+    
+    /// When a --source file is unreadable or empty, fetch entropy through the usual
+    /// source chain instead of exiting
+    #[arg(long = "source-fallback", requires = "source_file")]
+    source_fallback: bool,
+    
+    /// Read --source files (and the file given to `qcoin count`) as raw bytes, even
+    /// when their content would pass for hex, base64 or text
+    #[arg(long = "raw")]
+    raw: bool,
     
     /// Hex string to use as entropy source instead of quantum sources.
-    /// Can include optional 0x prefix (e.g., "abc123", "0xabc123").
-    #[arg(long = "hex", value_name = "HEX_STRING", conflicts_with_all = ["source_file"])]
+    /// Can include optional 0x prefix (e.g., "abc123", "0xabc123"); a 0b or 0o
+    /// prefix reads binary or octal digits instead.
+    #[arg(long = "hex", value_name = "HEX_STRING", group = "input")]
     hex_string: Option<String>,
+    
+    /// Warn when a --hex or --base64 string is longer than this many characters
+    /// (an error with --strict)
+    #[arg(long = "hex-limit", value_name = "CHARS", default_value_t = 1 << 20)]
+    hex_limit: usize,
+    
+    /// Base64 string to use as entropy source instead of quantum sources.
+    /// Both the standard and URL-safe alphabets are accepted, with or without padding.
+    #[arg(long = "base64", value_name = "BASE64_STRING", group = "input")]
+    base64_string: Option<String>,
+    
+    /// Encoding for saved entropy files, overriding the one implied by each output
+    /// file's extension (.bin raw, .hex/.txt hex, .b64 base64) [default: hex]
+    #[arg(long = "encoding", value_name = "ENCODING", global = true)]
+    encoding: Option<Encoding>,
+    
+    /// Query the quantum sources concurrently and use the first successful response
+    /// instead of trying them one after another.
+    #[arg(long = "race", global = true)]
+    race: bool,
+    
+    /// Fetch from every quantum source and XOR the responses together so no
+    /// single provider has to be trusted. Failed sources are left out of the mix.
+    #[arg(long = "mix", global = true, conflicts_with = "race")]
+    mix: bool,
+    
+    /// Never fall back to CSRNG: exit with an error if every quantum source and the
+    /// saved entropy file fail
+    #[arg(long = "quantum-only", visible_alias = "fail-fast", global = true)]
+    quantum_only: bool,
+    
+    /// Share fetched quantum entropy with later runs requesting the same number of
+    /// bytes within this many seconds, instead of fetching again. Off by default,
+    /// since every run sharing the window flips with the same bytes.
+    #[arg(long = "session-cache", value_name = "SECS", global = true)]
+    session_cache: Option<u64>,
+    
+    /// Try the saved entropy file before the network, to save bandwidth. Every run
+    /// then flips with the same saved bytes until new entropy is saved, so pair it
+    /// with --saved-max-age or --saved-reuse refuse to bound the reuse.
+    #[arg(long = "prefer-saved", global = true)]
+    prefer_saved: bool,
+    
+    /// With --prefer-saved, only use the saved file first if it was written less than
+    /// this many seconds ago; older files are still the fallback after the network
+    #[arg(long = "saved-max-age", value_name = "SECS", requires = "prefer_saved", global = true)]
+    saved_max_age: Option<u64>,
+    
+    /// What to do when the saved entropy was already used by an earlier run.
+    /// Defaults to refuse with --quantum-only or --strict and to warn otherwise.
+    #[arg(long = "saved-reuse", value_name = "POLICY", global = true)]
+    saved_reuse: Option<SavedReuse>,
+    
+    /// Never make network requests: entropy must come from --source, --hex, --base64
+    /// or the saved entropy file, and the run fails if none is available
+    #[arg(long = "offline", visible_alias = "no-network", global = true, conflicts_with_all = ["race", "mix"])]
+    offline: bool,
+    
+    /// Append entropy to the output file instead of overwriting it, building up a
+    /// pool across runs that is read back as a whole
+    #[arg(long = "append", global = true)]
+    append: bool,
+    
+    /// Compress saved entropy files. Compressed files are detected automatically when read.
+    #[arg(long = "compress", value_name = "FORMAT", global = true)]
+    compress: Option<Compression>,
+    
+    /// Report which entropy source would be used, and why others would be skipped,
+    /// without making any network requests
+    #[arg(long = "dry-run", global = true)]
+    dry_run: bool,
+    
+    /// Print provenance metadata (request ids, timestamps, types) from each successful
+    /// provider response
+    #[arg(long = "show-metadata", global = true)]
+    show_metadata: bool,
+    
+    /// Print how long the network fetch, CSRNG generation and bit counting took
+    /// (also shown with --verbose)
+    #[arg(long = "timings", global = true)]
+    timings: bool,
+    
+    /// Log request URLs, HTTP status, response sizes and timings to stderr (-vv for headers).
+    /// RUST_LOG, e.g. `RUST_LOG=qcoin=debug`, takes precedence.
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    
+    /// Format of the status lines and diagnostic log; json writes every event to stderr
+    #[arg(long = "log-format", value_name = "FORMAT", default_value = "pretty", global = true)]
+    log_format: LogFormat,
+    
+    /// Suppress status output and progress bars, printing only the result
+    #[arg(short = 'q', long = "quiet", global = true)]
+    quiet: bool,
+    
+    /// Disable colored output. Color is also disabled when the NO_COLOR
+    /// environment variable is set or stdout is not a terminal.
+    #[arg(long = "no-color", global = true)]
+    no_color: bool,
+    
+    /// Replace emoji prefixes with ASCII tags such as [INFO], [OK], [WARN]
+    /// and [ERR], for terminals and logs that can't show emoji
+    #[arg(long = "ascii", global = true)]
+    ascii: bool,
+    
+    /// Config file with default settings (defaults to ~/.config/qcoin/config.toml)
+    #[arg(long = "config", value_name = "FILE", global = true)]
+    config_file: Option<String>,
+    
+    /// HTTP request timeout in seconds for quantum sources
+    #[arg(long = "timeout", value_name = "SECS", default_value_t = 30, global = true)]
+    timeout_secs: u64,
+    
+    /// Base URL of an ANU QRNG compatible API, e.g. a self-hosted QRNG
+    #[arg(long = "anu-url", value_name = "URL", env = "QCOIN_ANU_URL", default_value = ANU_DEFAULT_URL, global = true)]
+    anu_url: String,
+    
+    /// API key for ANU's keyed API. When set, ANU requests go to --anu-api-url
+    /// instead of the legacy --anu-url endpoint.
+    #[arg(long = "anu-api-key", value_name = "KEY", env = "ANU_API_KEY", hide_env_values = true, global = true)]
+    anu_api_key: Option<String>,
+    
+    /// Base URL of ANU's keyed API, used when an API key is configured
+    #[arg(long = "anu-api-url", value_name = "URL", env = "QCOIN_ANU_API_URL", default_value = ANU_API_DEFAULT_URL, global = true)]
+    anu_api_url: String,
+    
+    /// Bytes per ANU uint8 request. Values above 1024 only take effect with an
+    /// --anu-url backend that allows them; the public API is capped at 1024.
+    #[arg(long = "chunk-size", value_name = "NUM_BYTES", global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: Option<u64>,
+    
+    /// Base URL of a qrandom.io compatible API, e.g. a self-hosted QRNG
+    #[arg(long = "qrandom-url", value_name = "URL", env = "QCOIN_QRANDOM_URL", default_value = QRANDOM_DEFAULT_URL, global = true)]
+    qrandom_url: String,
+    
+    /// PEM file of extra root certificates to trust, e.g. for a TLS-inspecting proxy
+    #[arg(long = "ca-bundle", value_name = "FILE", global = true)]
+    ca_bundle: Option<String>,
+    
+    /// Only accept servers whose leaf certificate has this SHA-256 fingerprint
+    /// (hex, colons allowed); repeat to allow several certificates
+    #[arg(long = "pin-sha256", value_name = "FINGERPRINT", global = true)]
+    pin_sha256: Vec<String>,
+    
+    /// Maximum ANU QRNG fetches per minute, shared across runs. A fetch split into
+    /// several chunk requests counts once.
+    #[arg(long = "rate-limit", value_name = "RPM", default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    rate_limit: u32,
+    
+    /// Don't pace ANU requests at all. Only takes effect with a custom --anu-url;
+    /// the public API keeps its --rate-limit.
+    #[arg(long = "no-delay", conflicts_with = "rate_limit", global = true)]
+    no_delay: bool,
+    
+    /// Accept qrandom.io responses with at least this many bytes instead of
+    /// requiring the full requested amount
+    #[arg(long = "min-bytes", value_name = "NUM_BYTES", global = true)]
+    min_bytes: Option<usize>,
+    
+    /// HTTP connect timeout in seconds (configurable via config file)
+    #[arg(skip = 10u64)]
+    connect_timeout_secs: u64,
+    
+    /// Try quantum sources in order of their recorded success rate and latency
+    /// (see `qcoin stats` and `qcoin bench --save`) instead of the configured order
+    #[arg(long = "prefer-fastest", global = true)]
+    prefer_fastest: bool,
+    
+    /// Order in which quantum sources are tried (configurable via config file)
+    #[arg(skip = vec![QuantumSource::Anu, QuantumSource::Qrandom])]
+    sources: Vec<QuantumSource>,
+    
+    /// Simulate a biased coin that lands heads with this probability (between 0 and 1).
+    /// Each flip draws a uniform value from the entropy instead of counting bits.
+    #[arg(long = "prob", value_name = "P", value_parser = parse_probability)]
+    prob: Option<f64>,
+    
+    /// Names for the two outcomes, heads (YES) first, e.g. `Tea,Coffee`
+    #[arg(long = "labels", value_name = "HEADS,TAILS", value_parser = parse_labels)]
+    labels: Option<Labels>,
+    
+    /// Format of the final result. Machine-readable formats suppress status output.
+    #[arg(long = "format", value_name = "FORMAT", default_value = "text")]
+    format: OutputFormat,
+    
+    /// Worker threads for the seeded flip loop; 0 uses all cores and 1 runs the
+    /// flips serially, which is handy for debugging
+    #[arg(long = "threads", value_name = "N", default_value_t = 0)]
+    threads: usize,
+    
+    /// Generator used for the seeded flips
+    #[arg(long = "csrng", value_name = "ALGORITHM", default_value = "chacha12")]
+    csrng: Csrng,
+    
+    /// How entropy shorter than the 32-byte seed is extended. `hash` spreads every
+    /// input bit over the seed; `cycle` repeats the bytes, so the seed is structured.
+    #[arg(long = "pad", value_name = "MODE", default_value = "hash")]
+    pad: SeedPadding,
+    
+    /// Write each flip's outcome as one bit (1 = heads) packed into bytes, most
+    /// significant bit first, with the final byte zero-padded
+    #[arg(long = "output-bits", value_name = "FILE")]
+    output_bits: Option<String>,
+    
+    /// Save the 32-byte seed derived for the seeded CSRNG flips to FILE as hex,
+    /// so the run can be reproduced from it
+    #[arg(long = "hex-out", value_name = "FILE")]
+    hex_out: Option<String>,
+    
+    /// Tally heads and tails per flip and print a 95% Wilson confidence interval for
+    /// the heads probability. Recomputes every flip, so huge runs take twice as long.
+    #[arg(long = "count-flips")]
+    count_flips: bool,
+    
+    /// Print the individual flips as a string such as `HTTHTHHT`, or `10010110`
+    /// with `--sequence binary`
+    #[arg(long = "sequence", value_name = "STYLE", num_args = 0..=1, default_missing_value = "ht")]
+    sequence: Option<SequenceStyle>,
+    
+    /// Remember a hash of each quantum fetch and warn when a provider serves
+    /// the same bytes again, or fetch again with `--dedupe refetch`
+    #[arg(long = "dedupe", value_name = "ACTION", num_args = 0..=1, default_missing_value = "warn", global = true)]
+    dedupe: Option<DedupeAction>,
+    
+    /// With --sequence, print at most this many flips, followed by an ellipsis
+    #[arg(long = "max-print", value_name = "FLIPS", default_value_t = 100, requires = "sequence")]
+    max_print: usize,
+    
+    /// Consume exactly one entropy bit per flip, fetching only ceil(flips / 8) bytes,
+    /// instead of seeding a CSRNG that generates 1024 bytes per flip
+    #[arg(long = "bits", conflicts_with_all = ["prob", "entropy_bytes"])]
+    bits: bool,
+    
+    /// Use each entropy bit for one flip, eight flips per byte, and only once
+    /// the bits run out continue from a CSRNG seeded with the entropy
+    #[arg(long = "flips-per-byte", conflicts_with_all = ["prob", "bits", "flip_unit"])]
+    flips_per_byte: bool,
+    
+    /// Hash all of the entropy into one fair bit per flip with a SHA-256 randomness
+    /// extractor, instead of counting the 1-bits and 0-bits of each flip's bytes
+    #[arg(long = "extract", conflicts_with_all = ["prob", "bits", "flips_per_byte", "flip_unit"])]
+    extract: bool,
+    
+    /// Bytes whose 1-bits and 0-bits are counted for each flip: the CSRNG bytes generated
+    /// per flip, and the leading entropy bytes of the direct flip [default: 1024 CSRNG
+    /// bytes per flip, with the direct flip counting all entropy bytes]
+    #[arg(long = "flip-unit", value_name = "NUM_BYTES", conflicts_with_all = ["prob", "bits"], value_parser = clap::value_parser!(u64).range(1..))]
+    flip_unit: Option<u64>,
+    
+    /// Run the whole fetch and flip experiment this many times and summarize the outcomes
+    #[arg(long = "repeat", value_name = "RUNS")]
+    repeat: Option<usize>,
+    
+    /// With --repeat, fetch fresh entropy for every run instead of fetching it all
+    /// up front and splitting it between runs
+    #[arg(long = "fresh-entropy", requires = "repeat", conflicts_with_all = ["hex_string", "base64_string", "source_file", "offline"])]
+    fresh_entropy: bool,
+    
+    /// With --repeat, draw each run's entropy from a pool that is refilled from the
+    /// network in the background whenever it runs low
+    #[arg(long = "entropy-pool", requires = "repeat", conflicts_with_all = ["fresh_entropy", "hex_string", "base64_string", "source_file", "offline"])]
+    entropy_pool: bool,
+    
+    /// Treat entropy quality warnings as hard errors
+    #[arg(long = "strict")]
+    strict: bool,
+    
+    /// Apply von Neumann debiasing to the entropy bits before flipping
+    #[arg(long = "debias")]
+    debias: bool,
+    
+    /// Whiten the entropy with ChaCha20 keyed by its own SHA-256 digest before flipping.
+    /// A deterministic transform that evens out bias; it adds no entropy.
+    #[arg(long = "whiten")]
+    whiten: bool,
+    
+    /// Print a SHA-256 fingerprint of the entropy with the result, so a run can be
+    /// tied back to the exact entropy it used
+    #[arg(long = "hash", global = true)]
+    hash: bool,
+    
+    /// Run the same flips a second time from pure CSRNG entropy and compare the two
+    /// tallies with a chi-square test
+    #[arg(long = "compare", conflicts_with = "repeat")]
+    compare: bool,
+    
+    /// Flip once every --interval seconds until Ctrl-C, drawing entropy from a pool
+    /// that is refilled in the background, then print a summary
+    #[arg(long = "watch", conflicts_with_all = ["repeat", "compare", "hex_string", "base64_string", "source_file"])]
+    watch: bool,
+    
+    /// Seconds between --watch flips
+    #[arg(long = "interval", value_name = "SECS", default_value_t = 1.0, requires = "watch", value_parser = parse_interval)]
+    interval: f64,
+    
+    /// Explain step by step how the entropy is turned into flips for this run
+    #[arg(long = "explain")]
+    explain: bool,
+    
+    /// Print statistical tests of the entropy: Shannon entropy and serial
+    /// autocorrelation of the bits at --lag
+    #[arg(long = "analyze")]
+    analyze: bool,
+    
+    /// Bit distance for the --analyze autocorrelation test
+    #[arg(long = "lag", value_name = "BITS", default_value_t = 1, requires = "analyze", value_parser = clap::value_parser!(u32).range(1..))]
+    lag: u32,
+    
+    /// Print a histogram of entropy byte values (16 buckets across 0-255)
+    #[arg(long = "histogram")]
+    histogram: bool,
+    
+    /// Write per-byte bit counts of the entropy to a CSV file (columns: index,byte_value,ones)
+    #[arg(long = "csv", value_name = "FILE")]
+    csv_file: Option<String>,
+    
+    /// Append a timestamped record of each result to this file, shaped by --format:
+    /// a line for text and tsv, a JSON object per line for json and jsonl
+    #[arg(long = "result-file", value_name = "FILE")]
+    result_file: Option<String>,
+    
+    /// Write Prometheus text-format metrics for the run to this file, replacing it
+    #[arg(long = "metrics", value_name = "FILE")]
+    metrics_file: Option<String>,
 }
 
-fn main() {
-    // Parse command line arguments using clap
-    let args = Args::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch random bytes through the entropy source chain and save them without flipping
+    Bytes {
+        /// Number of random bytes to fetch
+        #[arg(short = 'c', short_alias = 'n', long = "count", value_name = "NUM_BYTES", default_value = "1024")]
+        count: usize,
+        
+        /// File to write the bytes to, encoded according to its extension; repeat for
+        /// several files. Required unless --format raw-hex is given.
+        #[arg(short = 'o', long = "output", value_name = "FILE")]
+        output: Vec<String>,
+        
+        /// Write raw binary bytes (shorthand for --encoding binary)
+        #[arg(long = "binary")]
+        binary: bool,
+        
+        /// Where the bytes go
+        #[arg(long = "format", value_name = "FORMAT", default_value = "file")]
+        format: BytesFormat,
+        
+        /// With --format raw-hex, end the hex string with a newline
+        #[arg(long = "newline")]
+        newline: bool,
+    },
     
-    println!("🎲 \x1b[1mQuantum Coin Toss\x1b[0m");
-    println!();
-
-    // Validate number of flips
-    if args.num_flips == 0 {
-        eprintln!("❌ Number of flips must be greater than 0");
-        std::process::exit(1);
-    }
+    /// Verify the seeding and flip logic against known golden values, without network access
+    Selftest,
+    
+    /// Show how often each quantum source has succeeded across runs
+    Stats,
+    
+    /// Count the 1-bits and 0-bits of a file and report its Shannon entropy, without flipping
+    Count {
+        /// File to read; hex and base64 text is decoded as with --source
+        #[arg(value_name = "FILE")]
+        file: String,
+    },
+    
+    /// Print a uniform random integer between --min and --max, inclusive
+    Int {
+        /// Smallest possible value, down to the i64 minimum
+        #[arg(long = "min", value_name = "MIN", allow_negative_numbers = true, value_parser = parse_int_bound)]
+        min: i128,
+        
+        /// Largest possible value, up to the u64 maximum
+        #[arg(long = "max", value_name = "MAX", allow_negative_numbers = true, value_parser = parse_int_bound)]
+        max: i128,
+    },
+    
+    /// Pick one of the given items uniformly at random
+    Choose {
+        /// Items to choose from
+        #[arg(value_name = "ITEM", required = true)]
+        items: Vec<String>,
+    },
+    
+    /// Print the given items in a uniformly random order
+    Shuffle {
+        /// Items to shuffle
+        #[arg(value_name = "ITEM", required = true)]
+        items: Vec<String>,
+    },
+    
+    /// Write raw entropy bytes until Ctrl-C: quantum bytes from a pool refilled in
+    /// the background, stretched by a CSRNG seeded with them while the next fetch runs
+    Stream {
+        /// File or named pipe to write to, or - for stdout
+        #[arg(long = "to", value_name = "PATH", default_value = "-")]
+        to: String,
+        
+        /// CSRNG bytes written per quantum byte while the next fetch runs. With 0
+        /// only quantum bytes are written and the stream pauses for each fetch.
+        #[arg(long = "stretch", value_name = "RATIO", default_value_t = 1024)]
+        stretch: usize,
+    },
+    
+    /// Time a few small fetches from each configured quantum source and rank them
+    Bench {
+        /// Fetches per source
+        #[arg(short = 'r', long = "rounds", value_name = "ROUNDS", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+        rounds: u32,
+        
+        /// Bytes requested per fetch
+        #[arg(short = 'c', long = "count", value_name = "NUM_BYTES", default_value_t = 16)]
+        count: usize,
+        
+        /// Record the measurements in the statistics file, where --prefer-fastest uses them
+        #[arg(long = "save")]
+        save: bool,
+    },
+    
+    /// Flip coins interactively in a live terminal UI (falls back to the normal CLI
+    /// when stdout is not a terminal)
+    Tui,
+}
 
-    // Check if output file already exists and warn user
-    if Path::new(&args.output_file).exists() && args.output_file != DEFAULT_OUTPUT_FILE {
-        println!("\x1b[33m⚠️  Warning: File '{}' already exists, it may be overwritten\x1b[0m", args.output_file);
+impl Args {
+    /// Returns how many entropy bytes a single run needs
+    fn entropy_len(&self) -> usize {
+        if self.bits {
+            self.num_flips.div_ceil(8)
+        } else {
+            self.entropy_bytes
+        }
     }
-
-    println!("📊 Flips: {}", args.num_flips);
-    println!();
-
-    // Determine entropy source and fetch bytes
-    let (entropy_bytes, is_quantum, source_description) = if let Some(hex_string) = &args.hex_string {
-        // Use hex string as entropy
-        match parse_hex_string(hex_string) {
-            Ok(bytes) => {
-                if bytes.is_empty() {
-                    eprintln!("❌ Hex string is empty");
-                    std::process::exit(1);
-                }
-                
-                let description = if args.num_flips == 1 {
-                    // For single flip, always use bytes directly
-                    format!("🔤 Using hex string entropy ({} bytes - direct interpretation)", bytes.len())
-                } else if bytes.len() == 1024 {
-                    "🔤 Using hex string entropy (1024 bytes - perfect match)".to_string()
-                } else if bytes.len() < 1024 {
-                    format!("🔤 Using hex string entropy ({} bytes < 1024 - will seed CSRNG)", bytes.len())
-                } else {
-                    format!("🔤 Using hex string entropy ({} bytes > 1024 - will seed CSRNG)", bytes.len())
-                };
-                
-                (bytes, false, description)
-            },
-            Err(e) => {
-                eprintln!("❌ Failed to parse hex string: {}", e);
-                std::process::exit(1);
-            }
+    
+    /// Returns the name printed for a YES or NO outcome, from --labels if given
+    fn outcome_label(&self, yes: bool) -> &str {
+        match (&self.labels, yes) {
+            (Some(labels), true) => &labels.heads,
+            (Some(labels), false) => &labels.tails,
+            (None, true) => "YES",
+            (None, false) => "NO",
         }
-    } else if let Some(source_file) = &args.source_file {
-        // Use source file as entropy
-        match read_source_file(source_file) {
-            Ok(bytes) => {
-                if bytes.is_empty() {
-                    eprintln!("❌ Source file is empty");
-                    std::process::exit(1);
-                }
-                
-                let description = if args.num_flips == 1 {
-                    // For single flip, always use bytes directly
-                    format!("📁 Using file entropy ({} bytes - direct interpretation)", bytes.len())
-                } else if bytes.len() == 1024 {
-                    "📁 Using file entropy (1024 bytes - perfect match)".to_string()
-                } else if bytes.len() < 1024 {
-                    format!("📁 Using file entropy ({} bytes < 1024 - will seed CSRNG)", bytes.len())
-                } else {
-                    format!("📁 Using file entropy ({} bytes > 1024 - will seed CSRNG)", bytes.len())
-                };
-                
-                (bytes, false, description)
-            },
-            Err(e) => {
-                eprintln!("❌ Failed to read source file '{}': {}", source_file, e);
-                std::process::exit(1);
-            }
+    }
+    
+    /// Builds the library's fetch options from the URL, TLS, rate limit and
+    /// chunk size options, exiting if the CA bundle or a pin is unusable
+    fn fetch_options(&self) -> FetchOptions {
+        FetchOptions {
+            sources: self.sources.iter().map(|source| source.provider()).collect(),
+            timeout: Duration::from_secs(self.timeout_secs),
+            connect_timeout: Duration::from_secs(self.connect_timeout_secs),
+            anu_url: self.anu_url.clone(),
+            anu_api_key: self.anu_api_key.clone().filter(|key| !key.is_empty()),
+            anu_api_url: self.anu_api_url.clone(),
+            anu_chunk_size: self.chunk_size.map_or(ANU_MAX_CHUNK, |chunk_size| anu_chunk_size(chunk_size as usize, &self.anu_url)),
+            anu_rate_limit: (!self.anu_unthrottled()).then(|| Arc::new(RateLimiter::per_minute(self.rate_limit))),
+            qrandom_url: self.qrandom_url.clone(),
+            min_bytes: self.min_bytes,
+            ca_certs: self.ca_bundle.as_deref().map(load_ca_bundle).unwrap_or_default(),
+            cert_pins: self.pin_sha256.iter().map(|pin| parse_cert_pin(pin)).collect(),
+            csrng_fallback: !self.quantum_only,
         }
-    } else {
-        // Use quantum sources as before
-        let (quantum_bytes, is_quantum) = fetch_random_bytes_with_source(1024);
-        let description = if is_quantum {
-            "🌱 Using quantum entropy sources".to_string()
-        } else {
-            "🌱 Using saved quantum entropy".to_string()
-        };
-        (quantum_bytes, is_quantum, description)
-    };
+    }
     
-    println!("{}", source_description);
+    /// Whether ANU requests go unpaced: --no-delay was given for a custom --anu-url
+    fn anu_unthrottled(&self) -> bool {
+        self.no_delay && self.anu_url.trim_end_matches('/') != ANU_DEFAULT_URL
+    }
     
-    // Save quantum bytes to hex file only if we got them from quantum sources and not using source file
-    if is_quantum && args.source_file.is_none() {
-        save_quantum_bytes_to_file(&entropy_bytes, &args.output_file);
-    } else if args.hex_string.is_some() {
-        // Save hex string entropy to file for reuse
-        save_quantum_bytes_to_file(&entropy_bytes, &args.output_file);
-        println!("💾 Hex string entropy saved for future reuse");
+    /// Returns the --flip-unit, if one was given
+    fn flip_unit(&self) -> Option<usize> {
+        self.flip_unit.map(|unit| unit as usize)
     }
-
-    let (ones, zeros) = if args.num_flips == 1 {
-        // Single flip: use entropy bytes directly
-        println!("🔬 Using entropy directly");
-        let (q_ones, q_zeros) = count_bits(&entropy_bytes);
-        println!("🎲 Entropy bits: \x1b[36m{}\x1b[0m 1s : \x1b[36m{}\x1b[0m 0s (ratio: {})", q_ones, q_zeros, format_ratio(q_ones, q_zeros));
-        (q_ones, q_zeros)
-    } else {
-        // Multiple flips: N-1 flips using seeded CSRNG + 1 flip using entropy bytes directly
-        if entropy_bytes.len() < 1024 {
-            println!("🌱 Using {} bytes to seed {} flips ({} CSRNG + 1 direct)", entropy_bytes.len(), args.num_flips, args.num_flips - 1);
+    
+    /// Returns the entropy output files, falling back to the default when `-o` wasn't given
+    fn output_paths(&self) -> Vec<String> {
+        if self.output_file.is_empty() {
+            vec![default_output_path().to_string_lossy().into_owned()]
         } else {
-            println!("🌱 Using entropy to seed {} flips ({} CSRNG + 1 direct)", args.num_flips, args.num_flips - 1);
+            self.output_file.clone()
         }
-        let (total_ones, total_zeros, q_ones, q_zeros) = perform_multiple_flips(&entropy_bytes, args.num_flips);
-        println!("🎲 Direct entropy: \x1b[36m{}\x1b[0m 1s : \x1b[36m{}\x1b[0m 0s (ratio: {})", format_number_with_commas(q_ones as u64), format_number_with_commas(q_zeros as u64), format_ratio(q_ones, q_zeros));
-        (total_ones, total_zeros)
-    };
+    }
+    
+    /// Returns the policy for saved entropy an earlier run already used
+    fn saved_reuse(&self) -> SavedReuse {
+        match self.saved_reuse {
+            Some(policy) => policy,
+            None if self.quantum_only || self.strict => SavedReuse::Refuse,
+            None => SavedReuse::Warn,
+        }
+    }
     
-    println!();
-    println!("📈 Result: \x1b[36m{}\x1b[0m ones, \x1b[36m{}\x1b[0m zeros", format_number_with_commas(ones as u64), format_number_with_commas(zeros as u64));
+    /// Returns the options for writing the entropy file at `path`
+    /// Returns the encoding of the saved entropy file, as it is written by this run
+    fn saved_encoding(&self) -> Encoding {
+        self.save_options(&default_output_path().to_string_lossy()).encoding
+    }
     
-    if ones > zeros {
-        println!("🎯 Outcome: \x1b[1;32mYES\x1b[0m");
-    } else {
-        println!("🎯 Outcome: \x1b[1;31mNO\x1b[0m");
+    fn save_options(&self, path: &str) -> SaveOptions {
+        SaveOptions {
+            encoding: self.encoding.or_else(|| Encoding::from_path(path)).unwrap_or(Encoding::Hex),
+            compression: self.compress,
+            append: self.append,
+        }
+    }
+    
+    /// Fills in config file values for every setting not given explicitly on the command line
+    fn apply_config(&mut self, config: Config, matches: &ArgMatches) {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        
+        if let Some(sources) = config.sources {
+            self.sources = sources;
+        }
+        if let Some(timeout_secs) = config.timeout_secs && !from_cli("timeout_secs") {
+            self.timeout_secs = timeout_secs;
+        }
+        if let Some(connect_timeout_secs) = config.connect_timeout_secs {
+            self.connect_timeout_secs = connect_timeout_secs;
+        }
+        if self.output_file.is_empty() {
+            self.output_file.extend(config.output);
+        }
+        if config.color == Some(false) {
+            self.no_color = true;
+        }
+        // clap has already given the flag precedence over ANU_API_KEY
+        if self.anu_api_key.as_deref().is_none_or(str::is_empty) {
+            self.anu_api_key = config.anu_api_key;
+        }
+    }
+}
+
+/// Loads the config file given by `--config`, or the default one if it exists
+fn load_config(args: &Args) -> Config {
+    let path = match &args.config_file {
+        Some(path) => PathBuf::from(path),
+        None => match Config::default_path() {
+            Some(path) if path.exists() => path,
+            _ => return Config::default(),
+        },
+    };
+    
+    match Config::load(&path) {
+        Ok(config) => config,
+        Err(e) => {
+            fail(format!("Failed to load config file '{}': {}", path.display(), e));
+        }
+    }
+}
+
+fn main() {
+    // Parse command line arguments using clap, then layer config file defaults underneath
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config = load_config(&args);
+    args.apply_config(config, &matches);
+    helpers::set_quiet(args.quiet || args.format != OutputFormat::Text);
+    JSON_ERRORS.store(matches!(args.format, OutputFormat::Json | OutputFormat::Jsonl), Ordering::Relaxed);
+    helpers::set_ascii(args.ascii);
+    logging::init(args.verbose, args.log_format);
+    helpers::set_show_metadata(args.show_metadata);
+    if args.anu_unthrottled() {
+        verbose!(1, "🚦 ANU rate limit disabled for {} (--no-delay)", args.anu_url);
+    } else if args.no_delay {
+        status!("{}", yellow("⚠️  Warning: --no-delay only applies to a custom --anu-url, keeping the rate limit for the public ANU API"));
+    }
+    if args.prefer_fastest {
+        order_sources_by_stats(&mut args.sources);
+    }
+    
+    // Ctrl-C during the flip loop stops it so the partial tally is printed; anywhere
+    // else, or a second Ctrl-C, exits immediately
+    let _ = ctrlc::set_handler(|| {
+        if (FLIPPING.load(Ordering::Relaxed) || WATCHING.load(Ordering::Relaxed)) && !INTERRUPTED.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        std::process::exit(130);
+    });
+    timings::set_enabled(args.metrics_file.is_some(), args.timings || args.verbose > 0);
+    // Rayon only drives the flip loops, so sizing its global pool caps just those
+    if args.threads > 0 && let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(args.threads).build_global() {
+        verbose!(1, "⚠️  Failed to limit flip threads to {}: {}", args.threads, e);
+    }
+    helpers::set_color(
+        !args.no_color
+            && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            && std::io::stdout().is_terminal()
+            // JSON log messages carry plain text
+            && args.log_format == LogFormat::Pretty,
+    );
+    let _ = FETCH_OPTIONS.set(args.fetch_options());
+    
+    // Validate number of flips before any subcommand sizes its entropy from them
+    if args.num_flips == 0 {
+        fail("Number of flips must be greater than 0");
+    }
+    
+    if args.entropy_bytes == 0 {
+        fail("Number of entropy bytes must be greater than 0");
+    }
+    
+    match &args.command {
+        Some(Command::Bytes { count, output, binary, format, newline }) => {
+            if *format == BytesFormat::RawHex {
+                if !output.is_empty() || *binary {
+                    fail("--format raw-hex prints to stdout and can't be combined with -o or --binary");
+                }
+                // Only the hex string may reach stdout, so it can be captured as is
+                helpers::set_quiet(true);
+            } else if output.is_empty() {
+                fail("bytes needs -o FILE, or --format raw-hex to print the bytes to stdout");
+            }
+            dump_random_bytes(&args, *count, output, *binary, *newline);
+            timings::print_table();
+            return;
+        }
+        Some(Command::Selftest) => {
+            // Only the check results are printed, not the flip progress
+            helpers::set_quiet(true);
+            if !run_selftest() {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Stats) => {
+            print_source_stats();
+            return;
+        }
+        Some(Command::Count { file }) => {
+            run_count(file, args.strict, args.raw);
+            return;
+        }
+        Some(Command::Int { min, max }) => {
+            run_int(&args, *min, *max);
+            return;
+        }
+        Some(Command::Choose { items }) => {
+            run_choose(&args, items);
+            return;
+        }
+        Some(Command::Shuffle { items }) => {
+            run_shuffle(&args, items);
+            return;
+        }
+        Some(Command::Stream { to, stretch }) => {
+            if to == "-" {
+                if std::io::stdout().is_terminal() {
+                    fail("qcoin stream writes raw bytes; pipe stdout into a program or use --to PATH");
+                }
+                // Only the stream may reach stdout
+                helpers::set_quiet(true);
+            }
+            run_stream(&args, to, *stretch);
+            return;
+        }
+        Some(Command::Bench { rounds, count, save }) => {
+            run_bench(&args, *rounds, *count, *save);
+            return;
+        }
+        Some(Command::Tui) if std::io::stdout().is_terminal() => {
+            let (entropy_bytes, origin) = obtain_entropy(&args, args.entropy_len());
+            save_entropy(&args, &entropy_bytes, origin);
+            if let Err(e) = tui::run(qcoin::CoinFlipper::new(&entropy_bytes), origin.as_str()) {
+                fail(format!("Terminal UI failed: {}", e));
+            }
+            return;
+        }
+        Some(Command::Tui) => {
+            status!("{}", yellow("⚠️  Warning: stdout is not a terminal, running the normal CLI instead"));
+        }
+        None => {}
+    }
+    
+    status!("🎲 {}", bold("Quantum Coin Toss"));
+    status!();
+
+    if args.compare && args.format != OutputFormat::Text {
+        fail("--compare prints a text comparison and can't be combined with --format");
+    }
+    
+    if args.repeat == Some(0) {
+        fail("Number of runs must be greater than 0");
+    }
+
+    for output_file in args.output_paths() {
+        if let Err(e) = check_output_path(&output_file) {
+            fail(e);
+        }
+    }
+    
+    // Check if output file already exists and warn user
+    for output_file in &args.output_file {
+        if Path::new(output_file) != default_output_path() && Path::new(output_file).exists() {
+            status!("{}", yellow(format!("⚠️  Warning: File '{}' already exists, it may be overwritten", output_file)));
+        }
+    }
+
+    status!("📊 Flips: {}", args.num_flips);
+    status!();
+
+    if args.dry_run {
+        print_source_plan(&args, false, args.entropy_len());
+        return;
+    }
+    
+    match args.repeat {
+        Some(repeat) => run_repeated_experiments(&args, repeat),
+        None if args.compare => run_comparison(&args),
+        None if args.watch => run_watch(&args),
+        None => {
+            let (entropy_bytes, origin) = obtain_entropy(&args, args.entropy_len());
+            save_entropy(&args, &entropy_bytes, origin);
+            let result = run_experiment(&args, entropy_bytes, origin);
+            if let Some(path) = &args.result_file {
+                append_result_record(path, &result, args.format);
+            }
+            let mut totals = metrics::RunTotals::default();
+            totals.add(args.num_flips as u64, result.ones, result.zeros, result.source.as_str(), result.source.is_quantum());
+            write_metrics(&args, &totals);
+            match args.format {
+                OutputFormat::Text => {}
+                OutputFormat::Json => out!("{}", serde_json::to_string_pretty(&result).expect("result serializes")),
+                OutputFormat::Jsonl => out!("{}", serde_json::to_string(&result).expect("result serializes")),
+                OutputFormat::Tsv => out!("{}", result.to_tsv()),
+            }
+        }
+    }
+    
+    timings::print_table();
+    
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        std::process::exit(130);
+    }
+}
+
+/// Implements `--dry-run`: walks the source selection logic and reports which source
+/// would be used without touching the network. `network_only` is set for commands that
+/// ignore `--hex` and `--source`.
+fn print_source_plan(args: &Args, network_only: bool, num_bytes: usize) {
+    out!("🧪 {}", bold("Dry run: no requests will be made"));
+    
+    if !network_only {
+        if let Some(hex_string) = &args.hex_string {
+            out!("👉 Would use hex string entropy ({} characters)", hex_string.trim().len());
+            out!("⏭️  Skipping quantum sources, saved entropy and CSRNG: --hex was given");
+            return;
+        }
+        
+        if let Some(base64_string) = &args.base64_string {
+            out!("👉 Would use base64 string entropy ({} characters)", base64_string.trim().len());
+            out!("⏭️  Skipping quantum sources, saved entropy and CSRNG: --base64 was given");
+            return;
+        }
+        
+        if !args.source_file.is_empty() {
+            for source_file in &args.source_file {
+                match fs::metadata(source_file) {
+                    Ok(metadata) => out!("👉 Would use source file {} ({} bytes on disk)", cyan(source_file), metadata.len()),
+                    Err(e) => out!("👉 Would use source file {}, but it is not readable: {}", cyan(source_file), red(e)),
+                }
+            }
+            if !args.source_fallback {
+                out!("⏭️  Skipping quantum sources, saved entropy and CSRNG: --source was given");
+                return;
+            }
+            out!("↪️  If a source file can't be used, falling back to (--source-fallback):");
+        }
+    }
+    
+    let saved_path = default_output_path();
+    if args.offline {
+        if saved_path.exists() {
+            out!("👉 Would use saved entropy in {}", cyan(saved_path.display()));
+        } else {
+            out!("👉 No saved entropy in {}, so the run would fail", cyan(saved_path.display()));
+        }
+        out!("⏭️  Skipping quantum sources and CSRNG: --offline was given");
+        return;
+    }
+    
+    let mode = if args.mix {
+        "fetched concurrently and XOR-mixed"
+    } else if args.race {
+        "raced concurrently, first success wins"
+    } else {
+        "tried in order"
+    };
+    if args.prefer_saved {
+        match args.saved_max_age {
+            Some(secs) => out!("♻️  First: saved entropy in {}, if written less than {}s ago", cyan(saved_path.display()), secs),
+            None => out!("♻️  First: saved entropy in {}, if any", cyan(saved_path.display())),
+        }
+    }
+    out!("👉 Would request {} bytes from quantum sources ({}):", format_number_with_commas(num_bytes as u64), mode);
+    for (i, source) in args.sources.iter().enumerate() {
+        match fetch_options().anu_api_key.as_ref().filter(|_| *source == QuantumSource::Anu) {
+            Some(_) => out!("   {}. {} at {} (ready, API key configured)", i + 1, source.name(), fetch_options().anu_api_url.trim_end_matches('/')),
+            None => out!("   {}. {} at {} (ready, no API key required)", i + 1, source.name(), source.base_url()),
+        }
+    }
+    if args.sources.is_empty() {
+        out!("   {}", yellow("(no quantum sources configured)"));
+    }
+    out!("⏱️  Timeouts: {}s request, {}s connect", args.timeout_secs, args.connect_timeout_secs);
+    if args.anu_unthrottled() {
+        out!("🚦 ANU rate limit: off (--no-delay with a custom --anu-url)");
+    } else {
+        out!("🚦 ANU rate limit: {} requests per minute", args.rate_limit);
+    }
+    
+    if load_saved_quantum_bytes(args.saved_encoding()).is_ok_and(|bytes| saved_entropy_was_used(&bytes)) {
+        let action = match args.saved_reuse() {
+            SavedReuse::Refuse => "would be skipped",
+            SavedReuse::Allow | SavedReuse::Warn => "would be reused",
+        };
+        out!("♻️  Fallback: saved entropy in {} was already used and {}", cyan(saved_path.display()), action);
+    } else if saved_path.exists() {
+        out!("♻️  Fallback: saved entropy in {} is available", cyan(saved_path.display()));
+    } else {
+        out!("♻️  Fallback: no saved entropy in {}", cyan(saved_path.display()));
+    }
+    if args.quantum_only {
+        out!("⏭️  Skipping CSRNG: --quantum-only was given");
+    } else {
+        out!("🔐 Last resort: CSRNG (not quantum)");
+    }
+}
+
+/// Runs the flip logic on a fixed byte pattern and compares the tallies against
+/// golden values, returning whether every check passed
+fn run_selftest() -> bool {
+    out!("🧪 {}", bold("Self-test: seeding and flip logic"));
+    
+    let pattern: Vec<u8> = (0..1024u32).map(|i| (i * 7 + 3) as u8).collect();
+    let results = [
+        selftest_check("Single flip bit count", count_bits(&pattern), (4096, 4096)),
+        selftest_check("Seeded CSRNG flips", perform_multiple_flips(&pattern, 100, Csrng::default(), SeedPadding::Cycle, None, false), (409186, 410014, 4096, 4096)),
+        selftest_check("Short seed flips", perform_multiple_flips(&pattern[..5], 10, Csrng::default(), SeedPadding::Cycle, None, false), (36804, 36964, 13, 27)),
+        selftest_check("Hashed short seed flips", perform_multiple_flips(&pattern[..5], 10, Csrng::default(), SeedPadding::Hash, None, false), (36917, 36851, 13, 27)),
+        selftest_check("Weighted flips", perform_weighted_flips(&pattern[..70], 100, 0.3, Csrng::default(), SeedPadding::default()), (26, 74, 10)),
+        selftest_check("One bit per flip", count_flip_bits(&pattern, 100), (37, 63)),
+    ];
+    
+    let passed = results.iter().filter(|&&ok| ok).count();
+    if passed == results.len() {
+        out!("✅ {}", bold_green(format!("PASS: {}/{} checks", passed, results.len())));
+        true
+    } else {
+        out!("❌ {}", bold_red(format!("FAIL: {}/{} checks passed", passed, results.len())));
+        false
+    }
+}
+
+fn selftest_check<T: PartialEq + std::fmt::Debug>(name: &str, actual: T, expected: T) -> bool {
+    if actual == expected {
+        out!("   {} {}", green("PASS"), name);
+        true
+    } else {
+        out!("   {} {}: expected {:?}, got {:?}", red("FAIL"), name, expected, actual);
+        false
+    }
+}
+
+/// Reports a fatal error and exits with status 1. With a JSON --format the error
+/// is printed to stdout as `{"error": ..., "code": 1}`, where consumers already
+/// parse the results; otherwise it goes to stderr as text.
+fn fail(message: impl std::fmt::Display) -> ! {
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        out!("{}", serde_json::json!({ "error": message.to_string(), "code": 1 }));
+    } else {
+        err_out!("❌ {}", message);
+    }
+    std::process::exit(1);
+}
+
+/// Describes how user-provided entropy of `len` bytes will be used for `num_flips` flips
+fn describe_input_entropy(label: &str, len: usize, args: &Args) -> String {
+    if args.bits {
+        format!("{} ({} bytes - one bit per flip)", label, len)
+    } else if args.prob.is_some() {
+        format!("{} ({} bytes - 7 bytes per weighted flip, then a seeded CSRNG)", label, len)
+    } else if args.flips_per_byte {
+        format!("{} ({} bytes - one bit per flip, then a seeded CSRNG)", label, len)
+    } else if args.extract {
+        format!("{} ({} bytes - hashed into one fair bit per flip)", label, len)
+    } else if args.num_flips == 1 {
+        // For single flip, always use bytes directly
+        format!("{} ({} bytes - direct interpretation)", label, len)
+    } else if len < SEED_LEN {
+        format!("{} ({} bytes < {} - repeated to fill the CSRNG seed)", label, len, SEED_LEN)
+    } else {
+        format!("{} ({} bytes - first {} seed the CSRNG, all decide the direct flip)", label, len, SEED_LEN)
+    }
+}
+
+/// Prints how `entropy_len` bytes of entropy will be turned into flips in the chosen mode
+fn print_explanation(args: &Args, entropy_len: usize) {
+    let flips = args.num_flips;
+    let mut steps = Vec::new();
+    
+    if args.debias {
+        steps.push("Von Neumann debiasing kept only the unbiased bit pairs, leaving the bytes below".to_string());
+    }
+    if args.whiten {
+        steps.push("The bytes were XORed with a ChaCha20 keystream keyed by their SHA-256 digest".to_string());
+    }
+    steps.push(format!(
+        "The entropy holds {} bytes ({} bits)",
+        format_number_with_commas(entropy_len as u64),
+        format_number_with_commas(entropy_len as u64 * 8)
+    ));
+    
+    let seed_step = if entropy_len >= SEED_LEN {
+        format!("The first {} entropy bytes become the {} seed", SEED_LEN, args.csrng.name())
+    } else {
+        let padding = match args.pad {
+            SeedPadding::Cycle => "are repeated to fill",
+            SeedPadding::Zero => "are zero-padded to",
+            SeedPadding::Hash => "are hashed with SHA-256 into",
+        };
+        format!("The {} entropy bytes {} a {}-byte {} seed", entropy_len, padding, SEED_LEN, args.csrng.name())
+    };
+    
+    if args.bits {
+        steps.push(format!("Each of the {} flips reads the next bit, most significant bit first", flips));
+        steps.push("A 1 bit counts as a one and a 0 bit as a zero".to_string());
+        steps.push(format!("{} wins if there are more ones than zeros", args.outcome_label(true)));
+    } else if let Some(prob) = args.prob {
+        let direct = (entropy_len / WEIGHTED_BYTES_PER_FLIP).min(flips);
+        steps.push(format!(
+            "Each flip reads {} bytes and keeps the top 53 bits as a uniform value u in [0, 1)",
+            WEIGHTED_BYTES_PER_FLIP
+        ));
+        steps.push(format!("A flip lands heads when u < {}", prob));
+        steps.push(format!("{} flips read the entropy directly", format_number_with_commas(direct as u64)));
+        if direct < flips {
+            steps.push(seed_step);
+            steps.push(format!("The other {} flips read from that generator", format_number_with_commas((flips - direct) as u64)));
+        }
+        steps.push(format!("{} wins if there are more heads than tails", args.outcome_label(true)));
+    } else if args.flips_per_byte {
+        let direct = (entropy_len * 8).min(flips);
+        steps.push(format!("{} flips read one entropy bit each, most significant bit first", format_number_with_commas(direct as u64)));
+        if direct < flips {
+            steps.push(seed_step);
+            steps.push(format!("The other {} flips read one bit each from that generator", format_number_with_commas((flips - direct) as u64)));
+        }
+        steps.push(format!("A 1 bit counts as a one; {} wins if there are more ones than zeros", args.outcome_label(true)));
+    } else if args.extract {
+        steps.push("All entropy bytes are hashed with SHA-256 together with a block counter, 256 fair bits per block".to_string());
+        steps.push(format!("Each of the {} flips reads the next extracted bit", format_number_with_commas(flips as u64)));
+        steps.push(format!("A 1 bit counts as a one; {} wins if there are more ones than zeros", args.outcome_label(true)));
+    } else if flips == 1 {
+        steps.push(format!("The single flip counts every 1 bit and 0 bit in {} directly", direct_flip_scope(args.flip_unit())));
+        steps.push(format!("{} wins if there are more ones than zeros", args.outcome_label(true)));
+    } else {
+        let unit = args.flip_unit().unwrap_or(DEFAULT_FLIP_UNIT);
+        steps.push(seed_step);
+        steps.push(format!(
+            "Each of the {} CSRNG flips XORs its index into the seed and generates {} bytes ({} bytes in total)",
+            format_number_with_commas((flips - 1) as u64),
+            format_number_with_commas(unit as u64),
+            format_number_with_commas((flips - 1) as u64 * unit as u64)
+        ));
+        steps.push(format!("The final flip counts the bits of {} directly", direct_flip_scope(args.flip_unit())));
+        steps.push(format!("Ones and zeros are summed over every flip; {} wins if there are more ones", args.outcome_label(true)));
+    }
+    
+    status!("📖 {}", bold("How this run works:"));
+    for (i, step) in steps.iter().enumerate() {
+        status!("   {}. {}", i + 1, step);
+    }
+}
+
+/// Flags `--hex`/`--base64` strings longer than the `--hex-limit` soft limit
+fn input_size_warning(flag: &str, len: usize, limit: usize) -> Option<String> {
+    (len > limit).then(|| {
+        format!(
+            "{} string is {} characters, above the {} character limit; large inputs are better passed with --source <file>",
+            flag,
+            format_number_with_commas(len as u64),
+            format_number_with_commas(limit as u64)
+        )
+    })
+}
+
+/// Determines the entropy source, fetches `num_bytes` from it if it's a network
+/// source, and sanity-checks the result. Returns the bytes and whether they're quantum.
+fn obtain_entropy(args: &Args, num_bytes: usize) -> (Vec<u8>, EntropyOrigin) {
+    let input_string = args.hex_string.as_ref().map(|hex| ("--hex", hex)).or(args.base64_string.as_ref().map(|base64| ("--base64", base64)));
+    if let Some((flag, input)) = input_string && let Some(warning) = input_size_warning(flag, input.trim().len(), args.hex_limit) {
+        if args.strict {
+            fail(warning);
+        }
+        status!("{}", yellow(format!("⚠️  Warning: {}", warning)));
+    }
+    
+    let (entropy_bytes, origin, source_description) = if let Some(hex_string) = &args.hex_string {
+        // Use hex string as entropy
+        match parse_hex_string(hex_string) {
+            Ok(bytes) => {
+                if bytes.is_empty() {
+                    fail("Hex string is empty");
+                }
+                
+                let description = describe_input_entropy("🔤 Using hex string entropy", bytes.len(), args);
+                
+                (bytes, EntropyOrigin::Hex, description)
+            },
+            Err(e) => {
+                fail(format!("Failed to parse hex string: {}", e));
+            }
+        }
+    } else if let Some(base64_string) = &args.base64_string {
+        // Use base64 string as entropy
+        match parse_base64_string(base64_string) {
+            Ok(bytes) => {
+                let description = describe_input_entropy("🔤 Using base64 string entropy", bytes.len(), args);
+                (bytes, EntropyOrigin::Base64, description)
+            },
+            Err(e) => {
+                fail(format!("Failed to parse base64 string: {}", e));
+            }
+        }
+    } else if let Some(bytes) = read_source_files(args) {
+        let label = if args.source_file.len() > 1 {
+            format!("📁 Using entropy from {} files", args.source_file.len())
+        } else {
+            "📁 Using file entropy".to_string()
+        };
+        let description = describe_input_entropy(&label, bytes.len(), args);
+        
+        (bytes, EntropyOrigin::File, description)
+    } else {
+        // Use quantum sources as before
+        let (quantum_bytes, origin) = fetch_random_bytes_with_source(num_bytes, args);
+        let description = match origin {
+            EntropyOrigin::Saved => "🌱 Using saved quantum entropy".to_string(),
+            EntropyOrigin::Csrng => "🌱 Using CSRNG entropy".to_string(),
+            _ => "🌱 Using quantum entropy sources".to_string(),
+        };
+        (quantum_bytes, origin, description)
+    };
+    
+    status!("{}", source_description);
+    
+    let score = quality_score(&entropy_bytes);
+    let score_text = format!("{}/100", score);
+    let score_text = match score {
+        80.. => green(score_text),
+        50..80 => yellow(score_text),
+        _ => red(score_text),
+    };
+    status!("🩺 Quality score: {}", score_text);
+    
+    if let Some(warning) = entropy_sanity_warning(&entropy_bytes) {
+        if args.strict {
+            fail(warning);
+        }
+        status!("{}", yellow(format!("⚠️  Warning: {}", warning)));
+    }
+    
+    (entropy_bytes, origin)
+}
+
+/// Reads the --source files, concatenated in the order given. Returns `None` when
+/// there are none, or when one can't be used and --source-fallback hands over to
+/// the source chain; without it that is fatal.
+fn read_source_files(args: &Args) -> Option<Vec<u8>> {
+    if args.source_file.is_empty() {
+        return None;
+    }
+    
+    let mut bytes = Vec::new();
+    let mut failure = None;
+    for source_file in &args.source_file {
+        match read_source_file(source_file, args.strict, args.raw) {
+            Ok(file_bytes) => bytes.extend(file_bytes),
+            Err(e) => {
+                failure = Some(format!("Failed to read source file '{}': {}", source_file, e));
+                break;
+            }
+        }
+    }
+    if failure.is_none() && bytes.is_empty() {
+        failure = Some("Source file is empty".to_string());
+    }
+    
+    match failure {
+        None => Some(bytes),
+        Some(failure) if args.source_fallback => {
+            // Printed even when quiet, so the entropy is never mistaken for the file's
+            err_out!("{}", yellow(format!("⚠️  {}; falling back to the entropy sources (--source-fallback)", failure)));
+            None
+        }
+        Some(failure) => {
+            fail(failure);
+        }
+    }
+}
+
+/// Saves quantum bytes (or hex string entropy) to the output file for reuse
+fn save_entropy(args: &Args, entropy_bytes: &[u8], origin: EntropyOrigin) {
+    // Writing saved bytes back over the saved file would make them look fresh, and
+    // with --append duplicate them
+    let outputs = args.output_paths();
+    let to_saved_file = outputs.iter().any(|output| Path::new(output) == default_output_path());
+    if args.no_save || (origin == EntropyOrigin::Saved && to_saved_file) {
+        return;
+    }
+    
+    // Save quantum bytes to file only if we got them from quantum sources and not using source file
+    let save = || {
+        let results: Vec<bool> = outputs.iter().map(|output| save_quantum_bytes_to_file(entropy_bytes, output, args.save_options(output))).collect();
+        results.into_iter().all(|saved| saved)
+    };
+    let saved = if origin.is_quantum() && args.source_file.is_empty() {
+        save()
+    } else if args.hex_string.is_some() || args.base64_string.is_some() {
+        // Save hex or base64 string entropy to file for reuse
+        let saved = save();
+        status!("💾 Input string entropy saved for future reuse");
+        saved
+    } else {
+        false
+    };
+    
+    // This run already flips with the bytes it saved, so a later fallback to them is a reuse
+    if saved && to_saved_file && let Ok(bytes) = load_saved_quantum_bytes(args.saved_encoding()) {
+        mark_saved_entropy_used(&bytes);
+    }
+}
+
+/// Runs the flip pipeline over one entropy buffer, prints the result in text
+/// format and returns it, fingerprinting the entropy with --hash
+fn run_experiment(args: &Args, entropy_bytes: Vec<u8>, origin: EntropyOrigin) -> FlipResult {
+    let entropy_sha256 = args.hash.then(|| hex::encode(Sha256::digest(&entropy_bytes)));
+    
+    let mut result = flip_entropy(args, entropy_bytes, origin);
+    if let Some(hash) = &entropy_sha256 && args.format == OutputFormat::Text {
+        match result.seed_padding {
+            Some(pad) => out!("🔑 Entropy SHA-256: {} (short seed padded with {})", hash, pad.as_str()),
+            None => out!("🔑 Entropy SHA-256: {}", hash),
+        }
+    }
+    result.entropy_sha256 = entropy_sha256;
+    result.label = args.labels.as_ref().map(|_| args.outcome_label(result.yes).to_string());
+    result
+}
+
+fn flip_entropy(args: &Args, entropy_bytes: Vec<u8>, origin: EntropyOrigin) -> FlipResult {
+    let entropy_bytes = if args.debias {
+        let bits = bytes_to_bits(&entropy_bytes);
+        let debiased = von_neumann_debias(&bits);
+        // Drop the trailing partial byte so padding can't skew the bit counts
+        let debiased_bytes = bits_to_bytes(&debiased[..debiased.len() - debiased.len() % 8]);
+        status!(
+            "🧹 Von Neumann debiasing: {} of {} bits survived ({} bytes)",
+            cyan(format_number_with_commas(debiased.len() as u64)),
+            format_number_with_commas(bits.len() as u64),
+            debiased_bytes.len()
+        );
+        if debiased_bytes.is_empty() {
+            fail("Not enough entropy left after debiasing");
+        }
+        debiased_bytes
+    } else {
+        entropy_bytes
+    };
+    
+    let entropy_bytes = if args.whiten {
+        status!("🌀 Whitening {} bytes with ChaCha20 keyed by their SHA-256 digest", entropy_bytes.len());
+        chacha_whiten(&entropy_bytes)
+    } else {
+        entropy_bytes
+    };
+    
+    if args.explain {
+        print_explanation(args, entropy_bytes.len());
+    }
+    
+    if args.analyze {
+        print_analysis(&entropy_bytes, args.lag as usize);
+    }
+    
+    if args.histogram {
+        print_histogram(&entropy_bytes);
+    }
+    
+    if let Some(csv_file) = &args.csv_file {
+        match write_bit_counts_csv(&entropy_bytes, Path::new(csv_file)) {
+            Ok(()) => status!("📄 Wrote per-byte bit counts to CSV: {}", cyan(csv_file)),
+            Err(e) => err_out!("❌ Failed to write CSV '{}': {}", csv_file, e),
+        }
+    }
+    
+    // Bit mode and single flips use the entropy directly, so only seeded flips pad
+    let seed_padding = (entropy_bytes.len() < SEED_LEN && !args.bits && args.num_flips > 1).then_some(args.pad);
+    if let Some(pad) = seed_padding {
+        status!("🧩 Padding {} entropy bytes to a {}-byte seed with: {}", entropy_bytes.len(), SEED_LEN, pad.as_str());
+    }
+    
+    if let Some(hex_out) = &args.hex_out {
+        // Other modes use the entropy directly or only partly through the seed, so
+        // the seed alone would not reproduce their flips
+        let seeded = args.prob.is_none() && !args.bits && !args.flips_per_byte && !args.extract && args.num_flips > 1;
+        if !seeded {
+            status!("{}", yellow("⚠️  No seed to save: only the default flip mode decides its flips from the derived seed"));
+        } else {
+            let seed = hex::encode(derive_seed(&entropy_bytes, args.pad));
+            match fs::write(hex_out, format!("{}\n", seed)) {
+                Ok(()) => status!("🔑 Saved seed {} to: {}", seed, cyan(hex_out)),
+                Err(e) => err_out!("❌ Failed to write seed '{}': {}", hex_out, e),
+            }
+        }
+    }
+    
+    let entropy_used = entropy_used(args, entropy_bytes.len()) as u64;
+    let entropy_bits_per_flip = entropy_used as f64 * 8.0 / args.num_flips as f64;
+    status!(
+        "🧮 Entropy used: {} bytes for {} flips ({:.4} bits per flip)",
+        format_number_with_commas(entropy_used),
+        format_number_with_commas(args.num_flips as u64),
+        entropy_bits_per_flip
+    );
+    if entropy_bits_per_flip < MIN_BITS_PER_FLIP {
+        status!(
+            "{}",
+            yellow(format!(
+                "⚠️  Warning: Only {:.4} bits of entropy per flip; most of each flip's randomness comes from the seeded CSRNG",
+                entropy_bits_per_flip
+            ))
+        );
+    }
+    
+    // Outcomes are recomputed from the same seed, so they match the tallies below
+    let outcomes = (args.output_bits.is_some() || args.count_flips).then(|| flip_outcomes(args, &entropy_bytes, args.num_flips));
+    
+    if let Some(output_bits) = &args.output_bits
+        && let Some(outcomes) = &outcomes
+    {
+        match fs::write(output_bits, bits_to_bytes(outcomes)) {
+            Ok(()) => status!("📄 Wrote {} flip outcomes as bits to: {}", format_number_with_commas(outcomes.len() as u64), cyan(output_bits)),
+            Err(e) => err_out!("❌ Failed to write flip outcomes '{}': {}", output_bits, e),
+        }
+    }
+    
+    if args.count_flips
+        && args.format == OutputFormat::Text
+        && let Some(outcomes) = &outcomes
+    {
+        let heads = outcomes.iter().filter(|&&is_heads| is_heads).count();
+        let (low, high) = wilson_interval(heads, outcomes.len());
+        out!(
+            "🪙 Flips: {} heads, {} tails ({:.1}% heads, 95% CI {:.1}%–{:.1}%)",
+            cyan(format_number_with_commas(heads as u64)),
+            cyan(format_number_with_commas((outcomes.len() - heads) as u64)),
+            100.0 * heads as f64 / outcomes.len() as f64,
+            100.0 * low,
+            100.0 * high
+        );
+        print_fairness_test(heads as u64, (outcomes.len() - heads) as u64, args.prob.unwrap_or(0.5));
+    }
+    
+    if let Some(style) = args.sequence
+        && args.format == OutputFormat::Text
+    {
+        let outcomes = flip_outcomes(args, &entropy_bytes, args.max_print);
+        let (heads, tails) = match style {
+            SequenceStyle::Ht => ('H', 'T'),
+            SequenceStyle::Binary => ('1', '0'),
+        };
+        let mut sequence: String = outcomes.iter().map(|&is_heads| if is_heads { heads } else { tails }).collect();
+        if args.num_flips > args.max_print {
+            sequence.push('…');
+        }
+        out!("🪙 Sequence: {}", sequence);
+    }
+    
+    if let Some(prob) = args.prob {
+        let (heads, tails, direct_flips) = perform_weighted_flips(&entropy_bytes, args.num_flips, prob, args.csrng, args.pad);
+        status!(
+            "⚖️  Weighted coin (p = {}): {} flips from entropy directly, {} from seeded CSRNG",
+            prob,
+            format_number_with_commas(direct_flips as u64),
+            format_number_with_commas((args.num_flips - direct_flips) as u64)
+        );
+        
+        if args.format == OutputFormat::Text {
+            status!();
+            out!("📈 Result: {} heads, {} tails", cyan(format_number_with_commas(heads as u64)), cyan(format_number_with_commas(tails as u64)));
+            
+            if heads > tails {
+                out!("🎯 Outcome: {}", bold_green(args.outcome_label(true)));
+            } else {
+                out!("🎯 Outcome: {}", bold_red(args.outcome_label(false)));
+            }
+        }
+        return FlipResult { seed_padding, entropy_used, entropy_bits_per_flip, ..FlipResult::new(heads as u64, tails as u64, origin) };
+    }
+    
+    let (ones, zeros) = if args.bits {
+        // Bit mode: every flip is one entropy bit
+        let available = entropy_bytes.len() * 8;
+        if available < args.num_flips {
+            fail(format!("--bits needs {} bits of entropy, only {} available", args.num_flips, available));
+        }
+        status!("🔬 Using one entropy bit per flip");
+        count_flip_bits(&entropy_bytes, args.num_flips)
+    } else if args.flips_per_byte {
+        let direct = (entropy_bytes.len() * 8).min(args.num_flips);
+        status!(
+            "🔬 {} flips from entropy bits directly, {} extended by a seeded CSRNG",
+            cyan(format_number_with_commas(direct as u64)),
+            cyan(format_number_with_commas((args.num_flips - direct) as u64))
+        );
+        let ones = bit_flip_outcomes(&entropy_bytes, args.num_flips, args.csrng, args.pad).into_iter().filter(|&one| one).count() as u64;
+        (ones, args.num_flips as u64 - ones)
+    } else if args.extract {
+        status!("🔬 Hashing {} entropy bytes into {} fair bits", entropy_bytes.len(), format_number_with_commas(args.num_flips as u64));
+        let ones = extract_fair_bits(&entropy_bytes, args.num_flips).into_iter().filter(|&one| one).count() as u64;
+        (ones, args.num_flips as u64 - ones)
+    } else if args.num_flips == 1 {
+        // Single flip: use entropy bytes directly
+        status!("🔬 Using entropy directly");
+        let direct_bytes = direct_flip_bytes(&entropy_bytes, args.flip_unit());
+        let (q_ones, q_zeros) = count_bits(direct_bytes);
+        let (ones_run, zeros_run) = longest_runs(direct_bytes);
+        status!("🎲 Entropy bits: {} 1s : {} 0s (ratio: {}, longest runs: {} 1s, {} 0s)", cyan(q_ones), cyan(q_zeros), format_ratio(q_ones, q_zeros), ones_run, zeros_run);
+        (q_ones, q_zeros)
+    } else {
+        // Multiple flips: N-1 flips using seeded CSRNG + 1 flip using entropy bytes directly
+        if let Some(warning) = seed_capacity_warning(entropy_bytes.len(), args.num_flips) {
+            if args.strict {
+                fail(warning);
+            }
+            status!("{}", yellow(format!("⚠️  Warning: {}", warning)));
+        }
+        
+        if entropy_bytes.len() < args.entropy_len() {
+            status!("🌱 Using {} bytes to seed {} flips ({} CSRNG + 1 direct)", entropy_bytes.len(), args.num_flips, args.num_flips - 1);
+        } else {
+            status!("🌱 Using entropy to seed {} flips ({} CSRNG + 1 direct)", args.num_flips, args.num_flips - 1);
+        }
+        let (total_ones, total_zeros, q_ones, q_zeros) = perform_multiple_flips(&entropy_bytes, args.num_flips, args.csrng, args.pad, args.flip_unit(), !helpers::is_quiet() && std::io::stdout().is_terminal());
+        let (ones_run, zeros_run) = longest_runs(direct_flip_bytes(&entropy_bytes, args.flip_unit()));
+        status!(
+            "🎲 Direct entropy: {} 1s : {} 0s (ratio: {}, longest runs: {} 1s, {} 0s)",
+            cyan(format_number_with_commas(q_ones)),
+            cyan(format_number_with_commas(q_zeros)),
+            format_ratio(q_ones, q_zeros),
+            ones_run,
+            zeros_run
+        );
+        (total_ones, total_zeros)
+    };
+    
+    if args.format == OutputFormat::Text {
+        status!();
+        out!("📈 Result: {} ones, {} zeros", cyan(format_number_with_commas(ones)), cyan(format_number_with_commas(zeros)));
+        
+        if ones > zeros {
+            out!("🎯 Outcome: {}", bold_green(args.outcome_label(true)));
+        } else {
+            out!("🎯 Outcome: {}", bold_red(args.outcome_label(false)));
+        }
+    }
+    
+    FlipResult { seed_padding, entropy_used, entropy_bits_per_flip, ..FlipResult::new(ones, zeros, origin) }
+}
+
+/// Flips the obtained entropy and then pure CSRNG entropy of the same size, and
+/// prints both tallies with a chi-square test of whether they differ
+fn run_comparison(args: &Args) {
+    let (entropy_bytes, origin) = obtain_entropy(args, args.entropy_len());
+    save_entropy(args, &entropy_bytes, origin);
+    let entropy_len = entropy_bytes.len();
+    status!("🔬 {}", bold(format!("Run 1: {} entropy", origin.as_str())));
+    let first = run_experiment(args, entropy_bytes, origin);
+    
+    let csrng_bytes = match fetch_crypto_srng_bytes(entropy_len) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            fail(format!("CSRNG: {}", e));
+        }
+    };
+    status!();
+    status!("🔐 {}", bold("Run 2: CSRNG entropy"));
+    let second = run_experiment(args, csrng_bytes, EntropyOrigin::Csrng);
+    
+    out!();
+    out!("⚖️  {}", bold("Comparison"));
+    out!("   {:<8} {:>14} {:>14} {:>8} {:>8}", "Source", "Ones", "Zeros", "Ratio", "Outcome");
+    for result in [&first, &second] {
+        out!(
+            "   {:<8} {:>14} {:>14} {:>8.4} {:>8}",
+            result.source.as_str(),
+            format_number_with_commas(result.ones),
+            format_number_with_commas(result.zeros),
+            result.ratio,
+            args.outcome_label(result.is_yes())
+        );
+    }
+    
+    let chi_square = chi_square_2x2([first.ones, first.zeros], [second.ones, second.zeros]);
+    let verdict = if chi_square < CHI_SQUARE_CRITICAL_1DF {
+        green("no significant difference at the 5% level")
+    } else {
+        yellow("the tallies differ at the 5% level")
+    };
+    out!("📐 Chi-square (1 df): {:.3}, {} (critical value {})", chi_square, verdict, CHI_SQUARE_CRITICAL_1DF);
+}
+
+/// Returns the current time as an RFC 3339 UTC timestamp
+fn utc_now() -> String {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    format_utc_timestamp(now.as_secs())
+}
+
+/// Appends one timestamped result record to the --result-file
+fn append_result_record(path: &str, result: &FlipResult, format: OutputFormat) {
+    let record = result.to_record(format, &utc_now());
+    
+    let written = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", record));
+    if let Err(e) = written {
+        err_out!("❌ Failed to write result file '{}': {}", path, e);
+    }
+}
+
+/// Tallies the results of repeated runs. JSON Lines results are printed as each
+/// run finishes, and only the formats printed at the end keep the results.
+struct RunLog<'a> {
+    format: OutputFormat,
+    result_file: Option<&'a str>,
+    flips_per_run: u64,
+    totals: metrics::RunTotals,
+    results: Vec<FlipResult>,
+    runs: usize,
+    yes_count: usize,
+}
+
+impl<'a> RunLog<'a> {
+    fn new(args: &'a Args) -> Self {
+        RunLog {
+            format: args.format,
+            result_file: args.result_file.as_deref(),
+            flips_per_run: args.num_flips as u64,
+            totals: metrics::RunTotals::default(),
+            results: Vec::new(),
+            runs: 0,
+            yes_count: 0,
+        }
+    }
+    
+    fn push(&mut self, result: FlipResult) {
+        if let Some(path) = self.result_file {
+            append_result_record(path, &result, self.format);
+        }
+        self.totals.add(self.flips_per_run, result.ones, result.zeros, result.source.as_str(), result.source.is_quantum());
+        self.runs += 1;
+        if result.is_yes() {
+            self.yes_count += 1;
+        }
+        
+        match self.format {
+            OutputFormat::Text => {}
+            OutputFormat::Jsonl => {
+                out!("{}", serde_json::to_string(&result).expect("result serializes"));
+                let _ = std::io::stdout().flush();
+            }
+            OutputFormat::Json | OutputFormat::Tsv => self.results.push(result),
+        }
+    }
+}
+
+/// Runs the whole fetch and flip pipeline `repeat` times and summarizes the outcomes
+fn run_repeated_experiments(args: &Args, repeat: usize) {
+    let mut results = RunLog::new(args);
+    
+    if args.entropy_pool {
+        let run_len = args.entropy_len();
+        let pool = EntropyPool::new(run_len * POOL_RUNS, run_len * POOL_RUNS / 2);
+        status!("🏊 Pooling entropy for {} runs at a time, refilling below {}", POOL_RUNS, POOL_RUNS / 2);
+        
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut used_saved = false;
+                pool.run_refiller(|needed| refill_pool(args, needed, &mut used_saved, true))
+            });
+            
+            for run in 1..=repeat {
+                status!("🔁 {}", bold(format!("Run {}/{}", run, repeat)));
+                let Some((entropy_bytes, origin)) = pool.take(run_len) else {
+                    fail("Entropy pool ran dry");
+                };
+                results.push(run_experiment(args, entropy_bytes, origin));
+                status!();
+                if INTERRUPTED.load(Ordering::Relaxed) {
+                    break;
+                }
+            }
+            
+            // An in-flight refill finishes (or times out) before the scope ends
+            pool.close();
+        });
+    } else if args.fresh_entropy {
+        for run in 1..=repeat {
+            status!("🔁 {}", bold(format!("Run {}/{}", run, repeat)));
+            let (entropy_bytes, origin) = obtain_entropy(args, args.entropy_len());
+            save_entropy(args, &entropy_bytes, origin);
+            results.push(run_experiment(args, entropy_bytes, origin));
+            status!();
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+    } else {
+        // Fetch entropy for all runs at once and give each run its own slice
+        let (entropy_bytes, origin) = obtain_entropy(args, args.entropy_len().saturating_mul(repeat));
+        save_entropy(args, &entropy_bytes, origin);
+        
+        let chunk_len = entropy_bytes.len() / repeat;
+        if chunk_len == 0 {
+            fail(format!("Not enough entropy for {} runs: only {} bytes available", repeat, entropy_bytes.len()));
+        }
+        status!("✂️  Splitting {} bytes into {} runs of {} bytes", entropy_bytes.len(), repeat, chunk_len);
+        status!();
+        
+        for (run, chunk) in entropy_bytes.chunks_exact(chunk_len).take(repeat).enumerate() {
+            status!("🔁 {}", bold(format!("Run {}/{}", run + 1, repeat)));
+            results.push(run_experiment(args, chunk.to_vec(), origin));
+            status!();
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+    }
+    
+    if args.format == OutputFormat::Text && results.runs < repeat {
+        status!("{}", yellow(format!("⚠️  Interrupted: summarizing {} of {} runs", results.runs, repeat)));
+    }
+    print_run_summary(args, &results);
+}
+
+/// Fetches `needed` bytes for an entropy pool and, if `save` is set, saves them.
+/// Returns no bytes (which closes the pool) rather than refilling from the saved
+/// file twice.
+fn refill_pool(args: &Args, needed: usize, used_saved: &mut bool, save: bool) -> (Vec<u8>, EntropyOrigin) {
+    let (bytes, origin) = fetch_random_bytes_with_source(needed, args);
+    // Refilling from the same saved file again would reuse entropy, so stop instead
+    if origin == EntropyOrigin::Saved {
+        if *used_saved {
+            err_out!("❌ Saved entropy was already used to refill the pool");
+            return (Vec::new(), origin);
+        }
+        *used_saved = true;
+    }
+    if save {
+        save_entropy(args, &bytes, origin);
+    }
+    (bytes, origin)
+}
+
+/// Implements `qcoin stream`: writes each chunk of quantum entropy from a pool
+/// refilled in the background to `to`, and while the next chunk is fetched, up to
+/// `stretch` bytes per quantum byte from a CSRNG seeded with the latest chunk.
+/// Writes block while the reader lags, so the stream runs only as fast as it is
+/// read. It ends on Ctrl-C or when the reader goes away.
+fn run_stream(args: &Args, to: &str, stretch: usize) {
+    let mut out: Box<dyn Write> = if to == "-" {
+        Box::new(std::io::stdout().lock())
+    } else {
+        match fs::File::create(to) {
+            Ok(file) => Box::new(file),
+            Err(e) => fail(format!("Failed to open '{}': {}", to, e)),
+        }
+    };
+    let chunk_len = args.entropy_len();
+    let pool = EntropyPool::new(chunk_len * POOL_RUNS, chunk_len * POOL_RUNS / 2);
+    let (mut quantum_bytes, mut stretched_bytes) = (0u64, 0u64);
+    status!("🚰 Streaming entropy to {} until Ctrl-C", cyan(to));
+    
+    WATCHING.store(true, Ordering::Relaxed);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            // Streamed bytes are never saved, so no later run can reuse them
+            let mut used_saved = false;
+            pool.run_refiller(|needed| refill_pool(args, needed, &mut used_saved, false))
+        });
+        
+        let mut stretcher: Option<(Box<dyn RngCore + Send>, usize)> = None;
+        while !INTERRUPTED.load(Ordering::Relaxed) {
+            // Stretch while the next chunk is fetched, and wait for it once the allowance is spent
+            let can_stretch = stretcher.as_ref().is_some_and(|(_, left)| *left > 0);
+            let taken = if can_stretch { pool.try_take(chunk_len) } else { pool.take(chunk_len) };
+            let bytes = match (taken, &mut stretcher) {
+                (Some((bytes, _)), stretcher) => {
+                    *stretcher = Some((args.csrng.seeded(derive_seed(&bytes, args.pad)), bytes.len() * stretch));
+                    quantum_bytes += bytes.len() as u64;
+                    bytes
+                }
+                (None, Some((csrng, left))) if can_stretch => {
+                    let mut block = vec![0u8; STREAM_BLOCK.min(*left)];
+                    csrng.fill_bytes(&mut block);
+                    *left -= block.len();
+                    stretched_bytes += block.len() as u64;
+                    block
+                }
+                (None, _) => {
+                    err_out!("❌ Entropy pool ran dry");
+                    break;
+                }
+            };
+            
+            if let Err(e) = out.write_all(&bytes).and_then(|()| out.flush()) {
+                // A reader closing the pipe is the normal way for a stream to end
+                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                    err_out!("❌ Failed to write to '{}': {}", to, e);
+                }
+                break;
+            }
+        }
+        
+        // An in-flight refill finishes (or times out) before the scope ends
+        pool.close();
+    });
+    WATCHING.store(false, Ordering::Relaxed);
+    
+    // Printed even when quiet, since stdout may be carrying the stream itself
+    err_out!(
+        "🚰 Streamed {} bytes: {} quantum, {} stretched by the CSRNG",
+        format_number_with_commas(quantum_bytes + stretched_bytes),
+        format_number_with_commas(quantum_bytes),
+        format_number_with_commas(stretched_bytes)
+    );
+}
+
+/// Implements --watch: flips once per interval from a background-refilled pool
+/// until Ctrl-C, printing a timestamp with each result and a summary at the end
+fn run_watch(args: &Args) {
+    let mut results = RunLog::new(args);
+    let run_len = args.entropy_len();
+    let pool = EntropyPool::new(run_len * POOL_RUNS, run_len * POOL_RUNS / 2);
+    let interval = Duration::from_secs_f64(args.interval);
+    status!("👀 Flipping every {}s until Ctrl-C", args.interval);
+    status!();
+    
+    WATCHING.store(true, Ordering::Relaxed);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut used_saved = false;
+            pool.run_refiller(|needed| refill_pool(args, needed, &mut used_saved, true))
+        });
+        
+        for tick in 1.. {
+            let Some((entropy_bytes, origin)) = pool.take(run_len) else {
+                err_out!("❌ Entropy pool ran dry");
+                break;
+            };
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                break;
+            }
+            
+            let timestamp = utc_now();
+            let mut result = run_experiment(args, entropy_bytes, origin);
+            if args.format == OutputFormat::Text {
+                out!("🕒 Tick {} at {}", tick, timestamp);
+            }
+            result.timestamp = Some(timestamp);
+            results.push(result);
+            status!();
+            
+            // Sleep in short steps so Ctrl-C ends the wait promptly
+            let next_tick = Instant::now() + interval;
+            while !INTERRUPTED.load(Ordering::Relaxed) && Instant::now() < next_tick {
+                thread::sleep(next_tick.saturating_duration_since(Instant::now()).min(Duration::from_millis(50)));
+            }
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+        
+        // An in-flight refill finishes (or times out) before the scope ends
+        pool.close();
+    });
+    WATCHING.store(false, Ordering::Relaxed);
+    
+    print_run_summary(args, &results);
+}
+
+/// Prints the summary of several runs in the chosen format and writes the --metrics file
+fn print_run_summary(args: &Args, results: &RunLog) {
+    match args.format {
+        OutputFormat::Text => {
+            out!(
+                "🏆 Summary: {} {}, {} {} over {} runs",
+                bold_green(format!("{}:", args.outcome_label(true))),
+                results.yes_count,
+                bold_red(format!("{}:", args.outcome_label(false))),
+                results.runs - results.yes_count,
+                results.runs
+            );
+        }
+        OutputFormat::Json => out!("{}", serde_json::to_string_pretty(&results.results).expect("results serialize")),
+        OutputFormat::Jsonl => {}
+        OutputFormat::Tsv => {
+            for result in &results.results {
+                out!("{}", result.to_tsv());
+            }
+        }
+    }
+    
+    write_metrics(args, &results.totals);
+}
+
+/// Writes the --metrics file, if one was requested
+fn write_metrics(args: &Args, totals: &metrics::RunTotals) {
+    if let Some(path) = &args.metrics_file
+        && let Err(e) = metrics::write(path, totals, timings::total(Phase::Fetch))
+    {
+        err_out!("❌ Failed to write metrics file '{}': {}", path, e);
+    }
+}
+
+/// Prints a chi-square goodness-of-fit test of the heads/tails tally against the
+/// coin's heads probability, with its p-value and a verdict at α = 0.05
+fn print_fairness_test(heads: u64, tails: u64, heads_probability: f64) {
+    let chi_square = chi_square_goodness_of_fit(&[heads, tails], &[heads_probability, 1.0 - heads_probability]);
+    let p_value = chi_square_p_value_1df(chi_square);
+    let coin = if heads_probability == 0.5 { "fair".to_string() } else { format!("p = {}", heads_probability) };
+    let verdict = if p_value >= 0.05 {
+        green(format!("consistent with {} at α=0.05", coin))
+    } else {
+        yellow(format!("not consistent with {} at α=0.05", coin))
+    };
+    out!("📐 Goodness of fit: χ² = {:.3} (1 df), p = {:.4}, {}", chi_square, p_value, verdict);
+}
+
+/// Flags entropy that is constant or has suspiciously low Shannon entropy,
+/// which usually means a corrupt file or a broken API response
+fn entropy_sanity_warning(bytes: &[u8]) -> Option<String> {
+    if bytes.len() < 2 {
+        return None;
+    }
+    
+    if bytes.iter().all(|&byte| byte == bytes[0]) {
+        return Some(format!("Entropy is constant: all {} bytes are 0x{:02x}", bytes.len(), bytes[0]));
+    }
+    
+    // A buffer of n bytes can have at most log2(min(n, 256)) bits of entropy per byte
+    let max_entropy = (bytes.len().min(256) as f64).log2();
+    let entropy = shannon_entropy(bytes);
+    if entropy < max_entropy * LOW_ENTROPY_FRACTION {
+        return Some(format!(
+            "Entropy looks suspiciously low: {:.2} bits per byte (expected close to {:.2})",
+            entropy, max_entropy
+        ));
+    }
+    
+    None
+}
+
+/// Returns how many of `entropy_len` entropy bytes decide the flips: those read
+/// directly plus those that go into the CSRNG seed, each counted once
+fn entropy_used(args: &Args, entropy_len: usize) -> usize {
+    let num_flips = args.num_flips;
+    let seed_bytes = entropy_len.min(SEED_LEN);
+    
+    if args.bits {
+        num_flips.div_ceil(8).min(entropy_len)
+    } else if args.prob.is_some() {
+        let direct = (entropy_len / WEIGHTED_BYTES_PER_FLIP).min(num_flips);
+        let direct_bytes = direct * WEIGHTED_BYTES_PER_FLIP;
+        if direct < num_flips { direct_bytes.max(seed_bytes) } else { direct_bytes }
+    } else if args.flips_per_byte {
+        if entropy_len * 8 < num_flips { entropy_len } else { num_flips.div_ceil(8) }
+    } else if args.extract {
+        entropy_len
+    } else if num_flips == 1 {
+        direct_flip_bytes_len(entropy_len, args.flip_unit())
+    } else {
+        direct_flip_bytes_len(entropy_len, args.flip_unit()).max(seed_bytes)
+    }
+}
+
+/// Explains the quality tradeoff when `num_flips` exceeds what the entropy can seed independently
+fn seed_capacity_warning(entropy_len: usize, num_flips: usize) -> Option<String> {
+    let csrng_flips = num_flips.saturating_sub(1);
+    if csrng_flips == 0 {
+        return None;
+    }
+    
+    if entropy_len < SEED_LEN {
+        return Some(format!(
+            "Only {} bytes of entropy for a {}-byte seed; the bytes will be repeated, so the {} CSRNG flips are correlated",
+            entropy_len, SEED_LEN, csrng_flips
+        ));
+    }
+    
+    let independent_flips = entropy_len / SEED_LEN;
+    if csrng_flips > independent_flips {
+        return Some(format!(
+            "{} CSRNG flips requested but {} bytes of entropy can only seed {} independently ({} bytes each); the rest are derived from shared seed material",
+            format_number_with_commas(csrng_flips as u64), entropy_len, independent_flips, SEED_LEN
+        ));
+    }
+    
+    None
+}
+
+/// Prints the --analyze tests. An autocorrelation beyond two standard errors
+/// (`2/sqrt(pairs)`) is highlighted as a sign of structure in the bits.
+fn print_analysis(bytes: &[u8], lag: usize) {
+    status!("🔬 Entropy analysis ({} bytes):", format_number_with_commas(bytes.len() as u64));
+    status!("   Shannon entropy: {:.4} bits per byte", shannon_entropy(bytes));
+    
+    let pairs = (bytes.len() * 8).saturating_sub(lag);
+    match bit_autocorrelation(bytes, lag) {
+        Some(correlation) if correlation.abs() > 2.0 / (pairs as f64).sqrt() => status!(
+            "   Serial autocorrelation (lag {}): {}",
+            lag,
+            yellow(format!("{:+.4} (beyond the ±{:.4} expected by chance)", correlation, 2.0 / (pairs as f64).sqrt()))
+        ),
+        Some(correlation) => status!("   Serial autocorrelation (lag {}): {}", lag, green(format!("{:+.4}", correlation))),
+        None => status!("   Serial autocorrelation (lag {}): undefined for this input", lag),
+    }
+}
+
+/// Prints a text histogram of byte values, scaled to the terminal width
+fn print_histogram(bytes: &[u8]) {
+    const BUCKETS: usize = 16;
+    
+    let counts = byte_histogram(bytes, BUCKETS);
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    // Leave room for the "  240-255 │" label and the trailing count
+    let bar_width = terminal_width().saturating_sub(24).max(10);
+    let bucket_size = 256 / BUCKETS;
+    
+    status!("📊 Byte value histogram ({} bytes):", format_number_with_commas(bytes.len() as u64));
+    for (i, &count) in counts.iter().enumerate() {
+        let start = i * bucket_size;
+        status!(
+            "  {:>3}-{:<3} │{} {}",
+            start,
+            start + bucket_size - 1,
+            cyan(histogram_bar(count, max_count, bar_width)),
+            count
+        );
+    }
+}
+
+/// Counts ones and zeros among the first `num_flips` bits, most significant bit first
+fn count_flip_bits(bytes: &[u8], num_flips: usize) -> (u64, u64) {
+    timings::time(Phase::Counting, || {
+        let ones = bytes_to_bits(bytes).iter().take(num_flips).filter(|&&bit| bit).count() as u64;
+        (ones, num_flips as u64 - ones)
+    })
+}
+
+fn count_bits(bytes: &[u8]) -> (u64, u64) {
+    timings::time(Phase::Counting, || {
+        let mut ones = 0;
+        let mut zeros = 0;
+        
+        for byte in bytes {
+            ones += byte.count_ones() as u64;
+            zeros += byte.count_zeros() as u64;
+        }
+        
+        (ones, zeros)
+    })
+}
+
+fn format_ratio(ones: u64, zeros: u64) -> String {
+    let total = ones + zeros;
+    if total == 0 {
+        return "0.00".to_string();
+    }
+    let ratio = ones as f64 / total as f64;
+    format!("{:.3}", ratio)
+}
+
+/// Implements `qcoin bytes`: fetches `count` bytes and writes them to `output`, or prints
+/// their hex to stdout when there is none, without running any flip logic
+fn dump_random_bytes(args: &Args, count: usize, outputs: &[String], binary: bool, newline: bool) {
+    if count == 0 {
+        fail("Byte count must be greater than 0");
+    }
+    
+    for output in outputs {
+        if let Err(e) = check_output_path(output) {
+            fail(e);
+        }
+    }
+    
+    status!("🎲 {}", bold("Quantum Entropy Fetch"));
+    status!();
+    status!("📊 Bytes: {}", format_number_with_commas(count as u64));
+    status!();
+    
+    if args.dry_run {
+        print_source_plan(args, true, count);
+        return;
+    }
+    
+    let (mut bytes, _) = fetch_random_bytes_with_source(count, args);
+    if bytes.len() > count {
+        bytes.truncate(count);
+    } else if bytes.len() < count {
+        status!("{}", yellow(format!("⚠️  Warning: Only {} of {} requested bytes are available", bytes.len(), count)));
+    }
+    
+    if outputs.is_empty() {
+        print!("{}", hex::encode(&bytes));
+        if newline {
+            out!();
+        }
+        let _ = std::io::stdout().flush();
+        return;
+    }
+    
+    let mut failed = false;
+    for output in outputs {
+        let mut options = args.save_options(output);
+        if binary {
+            options.encoding = Encoding::Binary;
+        }
+        failed |= !save_quantum_bytes_to_file(&bytes, output, options);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Returns where entropy is saved when `-o` isn't given and where saved entropy is
+/// reused from: the platform cache directory (e.g. `~/.cache/qcoin/qrandom.bytes`),
+/// or the working directory if no home directory is known
+fn default_output_path() -> PathBuf {
+    match directories::ProjectDirs::from("", "", "qcoin") {
+        Some(dirs) => dirs.cache_dir().join(DEFAULT_OUTPUT_FILE),
+        None => PathBuf::from(DEFAULT_OUTPUT_FILE),
+    }
+}
+
+/// Returns where per-source statistics are kept: the platform data directory
+/// (e.g. `~/.local/share/qcoin/stats.json`), or the working directory if no home
+/// directory is known
+fn stats_path() -> PathBuf {
+    match directories::ProjectDirs::from("", "", "qcoin") {
+        Some(dirs) => dirs.data_dir().join("stats.json"),
+        None => PathBuf::from("qcoin-stats.json"),
+    }
+}
+
+/// Implements `qcoin count`: prints the bit tally and Shannon entropy of a file
+fn run_count(file: &str, strict: bool, raw: bool) {
+    let bytes = match read_source_file(file, strict, raw) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            fail(format!("Failed to read '{}': {}", file, e));
+        }
+    };
+    
+    let (ones, zeros) = count_bits(&bytes);
+    let ratio = if bytes.is_empty() { 0.0 } else { ones as f64 / (ones + zeros) as f64 };
+    
+    out!("📊 Bits: {} ones, {} zeros (ratio {:.4})", cyan(format_number_with_commas(ones)), cyan(format_number_with_commas(zeros)), ratio);
+    out!("📐 Shannon entropy: {:.4} bits per byte", shannon_entropy(&bytes));
+}
+
+/// Result of `qcoin int` in the JSON output formats
+#[derive(Serialize)]
+struct IntResult {
+    value: i128,
+    min: i128,
+    max: i128,
+    source: EntropyOrigin,
+}
+
+/// Implements `qcoin int`: prints a uniform integer in `min..=max`
+fn run_int(args: &Args, min: i128, max: i128) {
+    if min > max {
+        fail(format!("--min {} is greater than --max {}", min, max));
+    }
+    
+    let (entropy_bytes, origin) = obtain_entropy(args, args.entropy_len());
+    let mut picker = entropy_picker(args, entropy_bytes);
+    let value = picker.between(min, max);
+    let (direct, extended) = picker.consumed();
+    verbose!(1, "🔢 Drew from {} entropy bytes and {} CSRNG bytes", direct, extended);
+    
+    match args.format {
+        OutputFormat::Text => {
+            status!();
+            out!("🎯 Number: {}", bold_green(value));
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let result = IntResult { value, min, max, source: origin };
+            out!("{}", serde_json::to_string(&result).expect("result serializes"));
+        }
+        OutputFormat::Tsv => out!("{}\t{}", value, origin.as_str()),
+    }
+}
+
+/// Result of `qcoin choose` in the JSON output formats
+#[derive(Serialize)]
+struct ChoiceResult<'a> {
+    choice: &'a str,
+    /// Position of the choice among the items, from 0
+    index: usize,
+    /// Where the entropy came from, absent when a single item needed none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<EntropyOrigin>,
+}
+
+/// Implements `qcoin choose`: prints one of `items`, picked uniformly
+fn run_choose(args: &Args, items: &[String]) {
+    let (index, origin) = if items.len() == 1 {
+        status!("{}", yellow("⚠️  Only one item to choose from, so no entropy is needed"));
+        (0, None)
+    } else {
+        let (entropy_bytes, origin) = obtain_entropy(args, args.entropy_len());
+        (entropy_picker(args, entropy_bytes).below(items.len() as u128) as usize, Some(origin))
+    };
+    
+    match args.format {
+        OutputFormat::Text => {
+            status!();
+            out!("🎯 Choice: {}", bold_green(&items[index]));
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let result = ChoiceResult { choice: &items[index], index, source: origin };
+            out!("{}", serde_json::to_string(&result).expect("result serializes"));
+        }
+        OutputFormat::Tsv => out!("{}\t{}", index, items[index]),
+    }
+}
+
+/// Result of `qcoin shuffle` in the JSON output formats
+#[derive(Serialize)]
+struct ShuffleResult<'a> {
+    items: Vec<&'a str>,
+    /// Original position of each shuffled item, from 0
+    permutation: Vec<usize>,
+    /// Where the entropy came from, absent when a single item needed none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<EntropyOrigin>,
+}
+
+/// Implements `qcoin shuffle`: prints `items` in a uniformly random order
+fn run_shuffle(args: &Args, items: &[String]) {
+    let (permutation, origin) = if items.len() == 1 {
+        status!("{}", yellow("⚠️  Only one item to shuffle, so no entropy is needed"));
+        (vec![0], None)
+    } else {
+        let (entropy_bytes, origin) = obtain_entropy(args, args.entropy_len());
+        (entropy_picker(args, entropy_bytes).permutation(items.len()), Some(origin))
+    };
+    let shuffled: Vec<&str> = permutation.iter().map(|&index| items[index].as_str()).collect();
+    
+    match args.format {
+        OutputFormat::Text => {
+            status!();
+            out!("🔀 Shuffled:");
+            for (position, item) in shuffled.iter().enumerate() {
+                out!("   {}. {}", position + 1, item);
+            }
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let result = ShuffleResult { items: shuffled, permutation, source: origin };
+            out!("{}", serde_json::to_string(&result).expect("result serializes"));
+        }
+        OutputFormat::Tsv => {
+            for (index, item) in permutation.iter().zip(shuffled) {
+                out!("{}\t{}", index, item);
+            }
+        }
+    }
+}
+
+/// Returns a picker over `entropy`, continuing from the --csrng seeded with it
+fn entropy_picker(args: &Args, entropy: Vec<u8>) -> Picker {
+    let csrng = args.csrng.seeded(derive_seed(&entropy, args.pad));
+    Picker::new(entropy, csrng)
+}
+
+/// Parses an integer between the i64 minimum and the u64 maximum
+fn parse_int_bound(value: &str) -> Result<i128, String> {
+    let number: i128 = value.trim().replace('_', "").parse().map_err(|_| format!("'{}' is not a whole number", value))?;
+    if number < i64::MIN as i128 || number > u64::MAX as i128 {
+        return Err(format!("'{}' is outside the supported range {}..={}", value, i64::MIN, u64::MAX));
+    }
+    Ok(number)
+}
+
+/// Implements `qcoin stats`: prints attempts, success rate and latency per source
+fn print_source_stats() {
+    let path = stats_path();
+    let stats = match stats::load(&path) {
+        Ok(stats) => stats,
+        Err(e) => {
+            fail(format!("Failed to read statistics '{}': {}", path.display(), e));
+        }
+    };
+    
+    out!("📊 {} ({})", bold("Source statistics"), cyan(path.display()));
+    if stats.is_empty() {
+        out!("   {}", yellow("No fetches recorded yet"));
+        return;
+    }
+    
+    out!("   {:<12} {:>10} {:>10} {:>8} {:>12}", "Source", "Attempts", "Successes", "Rate", "Avg latency");
+    for source in QuantumSource::ALL {
+        let Some(source_stats) = stats.get(source.id()) else {
+            continue;
+        };
+        let latency = source_stats
+            .average_latency()
+            .map_or("-".to_string(), |latency| format!("{} ms", latency.as_millis()));
+        out!(
+            "   {:<12} {:>10} {:>10} {:>7.1}% {:>12}",
+            source.name(),
+            format_number_with_commas(source_stats.attempts),
+            format_number_with_commas(source_stats.successes),
+            source_stats.success_rate() * 100.0,
+            latency
+        );
+    }
+}
+
+/// Fetches `count` bytes from each configured source `rounds` times and prints the
+/// sources ranked by success rate, then average latency
+fn run_bench(args: &Args, rounds: u32, count: usize, save: bool) {
+    if args.offline {
+        fail("qcoin bench needs network access, but --offline was given");
+    }
+    
+    let client = build_client();
+    // Every round must deliver the full amount, whatever --min-bytes allows
+    let options = FetchOptions { min_bytes: None, ..fetch_options().clone() };
+    let mut results = Vec::new();
+    
+    out!("⏱️  {} ({} rounds of {} bytes per source)", bold("Benchmarking quantum sources"), rounds, count);
+    // Only the bench's own lines are printed, not each fetch's status output
+    let was_quiet = helpers::is_quiet();
+    helpers::set_quiet(true);
+    for &source in &args.sources {
+        let mut source_stats = stats::SourceStats::default();
+        for round in 1..=rounds {
+            let start = Instant::now();
+            let result = source.fetch(&client, count, &options);
+            let latency = start.elapsed();
+            
+            source_stats.attempts += 1;
+            match result {
+                Ok(_) => {
+                    source_stats.successes += 1;
+                    source_stats.success_latency_ms += latency.as_millis() as u64;
+                    out!("   {} round {}: {} ms", source.name(), round, latency.as_millis());
+                }
+                Err(e) => out!("   {} round {}: {}", source.name(), round, red(format!("failed ({})", e))),
+            }
+        }
+        results.push((source, source_stats));
+    }
+    helpers::set_quiet(was_quiet);
+    
+    results.sort_by(|(_, a), (_, b)| a.rank_cmp(b));
+    out!();
+    out!("   {:<4} {:<12} {:>8} {:>12}", "Rank", "Source", "Rate", "Avg latency");
+    for (rank, (source, source_stats)) in results.iter().enumerate() {
+        let latency = source_stats
+            .average_latency()
+            .map_or("-".to_string(), |latency| format!("{} ms", latency.as_millis()));
+        out!("   {:<4} {:<12} {:>7.1}% {:>12}", rank + 1, source.name(), source_stats.success_rate() * 100.0, latency);
+    }
+    
+    if save {
+        let path = stats_path();
+        match stats::persist(&path) {
+            Ok(()) => out!("💾 Saved measurements to {}", cyan(path.display())),
+            Err(e) => err_out!("❌ Failed to update statistics '{}': {}", path.display(), e),
+        }
+    }
+}
+
+/// Reorders `sources` best first by their recorded statistics. Sources without
+/// statistics keep their relative order after the measured ones.
+fn order_sources_by_stats(sources: &mut [QuantumSource]) {
+    let stats = match stats::load(&stats_path()) {
+        Ok(stats) => stats,
+        Err(e) => {
+            verbose!(1, "⚠️  Failed to read source statistics, keeping the configured order: {}", e);
+            return;
+        }
+    };
+    
+    sources.sort_by(|a, b| match (stats.get(a.id()), stats.get(b.id())) {
+        (Some(a), Some(b)) => a.rank_cmp(b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    verbose!(1, "🏎️  Source order by recorded performance: {}", sources.iter().map(|source| source.name()).collect::<Vec<_>>().join(", "));
+}
+
+/// Rejects output paths that point at an existing directory
+fn check_output_path(output_file: &str) -> Result<(), String> {
+    let path = Path::new(output_file);
+    if path.is_dir() {
+        return Err(format!(
+            "Output path '{}' is a directory; please give a file name, e.g. '{}'",
+            output_file,
+            path.join(DEFAULT_OUTPUT_FILE).display()
+        ));
+    }
+    Ok(())
+}
+
+/// Saves entropy in the given encoding, optionally compressed and appended to the
+/// existing file, returning whether the write succeeded
+fn save_quantum_bytes_to_file(bytes: &[u8], output_file: &str, options: SaveOptions) -> bool {
+    if let Err(e) = check_output_path(output_file) {
+        err_out!("❌ Failed to save: {}", e);
+        return false;
+    }
+    
+    // fs::write won't create missing parent directories, so create them first
+    if let Some(parent) = Path::new(output_file).parent()
+        && !parent.as_os_str().is_empty()
+        && !parent.exists()
+    {
+        match fs::create_dir_all(parent) {
+            Ok(()) => status!("📂 Created directory: {}", cyan(parent.display())),
+            Err(e) => {
+                err_out!("❌ Failed to create directory '{}': {}", parent.display(), e);
+                return false;
+            }
+        }
+    }
+    
+    let path = Path::new(output_file);
+    let appending = options.append && path.metadata().is_ok_and(|metadata| metadata.len() > 0);
+    
+    let mut contents = Vec::new();
+    if appending && options.encoding != Encoding::Binary {
+        // Text encodings are appended one chunk per line so each stays decodable
+        contents.push(b'\n');
+    }
+    contents.extend(options.encoding.encode(bytes));
+    
+    // Concatenated gzip members and zstd frames decompress as one stream, so
+    // compressed chunks can be appended too
+    if let Some(compression) = options.compression {
+        match compression.compress(&contents) {
+            Ok(compressed) => contents = compressed,
+            Err(e) => {
+                err_out!("❌ Failed to compress: {}", e);
+                return false;
+            }
+        }
+    }
+    let result = if appending {
+        fs::OpenOptions::new().append(true).open(path).and_then(|mut file| file.write_all(&contents))
+    } else {
+        fs::write(path, contents)
+    };
+    
+    match result {
+        Ok(_) if appending => {
+            status!("💾 Appended quantum entropy to file: {}", cyan(output_file));
+            true
+        }
+        Ok(_) => {
+            status!("💾 Saved quantum entropy to file: {}", cyan(output_file));
+            true
+        }
+        Err(e) => {
+            err_out!("❌ Failed to save: {}", e);
+            false
+        }
     }
 }
 
-fn count_bits(bytes: &[u8]) -> (u32, u32) {
-    let mut ones = 0;
-    let mut zeros = 0;
+/// Reads entropy from a hex, base64, text or binary file. Under `strict`, hex with an
+/// odd number of digits is an error instead of being read as raw text; with `raw`
+/// the bytes are used as they are, with no decoding attempted.
+fn read_source_file(file_path: &str, strict: bool, raw: bool) -> Result<Vec<u8>, CrngError> {
+    let data = read_maybe_compressed(Path::new(file_path))?;
     
-    for byte in bytes {
-        ones += byte.count_ones();
-        zeros += byte.count_zeros();
+    if raw {
+        status!("📁 Reading {} bytes from file as raw bytes (--raw): {}", data.len(), cyan(file_path));
+        return Ok(data);
     }
     
-    (ones, zeros)
-}
-
-fn format_ratio(ones: u32, zeros: u32) -> String {
-    let total = ones + zeros;
-    if total == 0 {
-        return "0.00".to_string();
-    }
-    let ratio = ones as f64 / total as f64;
-    format!("{:.3}", ratio)
-}
-
-fn save_quantum_bytes_to_file(bytes: &[u8], output_file: &str) {
-    let hex_string = hex::encode(bytes);
-    match fs::write(output_file, hex_string) {
-        Ok(_) => println!("💾 Saved quantum entropy to file: \x1b[36m{}\x1b[0m", output_file),
-        Err(e) => eprintln!("❌ Failed to save: {}", e),
+    // A file named for its encoding, as -o writes them, is read in that encoding
+    match Encoding::from_path(file_path) {
+        Some(Encoding::Binary) => {
+            status!("📁 Reading {} bytes from binary file: {}", data.len(), cyan(file_path));
+            return Ok(data);
+        }
+        Some(Encoding::Base64) => {
+            if let Some(bytes) = decode_saved_text(&data, Encoding::Base64) {
+                status!("📁 Reading {} bytes from base64 string in source file: {}", bytes.len(), cyan(file_path));
+                return Ok(bytes);
+            }
+        }
+        Some(Encoding::Hex) | None => {}
     }
-}
-
-fn read_source_file(file_path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    
     // First try to read as text (for hex strings)
-    match fs::read_to_string(file_path) {
+    match String::from_utf8(data) {
         Ok(content) => {
             let trimmed = content.trim();
             
-            if trimmed.len() > 0 {
-                // Try to handle hex string (with or without 0x prefix)
-                let hex_str = if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
-                    &trimmed[2..] // Remove 0x prefix
+            if !trimmed.is_empty() {
+                // Try to handle hex string (with or without 0x prefix), ignoring line
+                // breaks so appended entropy pools decode as one string
+                let compact: String = trimmed.split_whitespace().collect();
+                let hex_str = if compact.starts_with("0x") || compact.starts_with("0X") {
+                    &compact[2..] // Remove 0x prefix
                 } else {
-                    trimmed
+                    &compact
                 };
                 
                 // Check if it looks like a hex string (only contains hex characters and even length)
-                if hex_str.len() > 0 && hex_str.len() % 2 == 0 && hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+                if !hex_str.is_empty() && hex_str.len().is_multiple_of(2) && hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
                     // Try to decode as hex
                     match hex::decode(hex_str) {
                         Ok(bytes) => {
-                            println!("📁 Reading {} bytes from hex string in source file: \x1b[36m{}\x1b[0m", bytes.len(), file_path);
+                            status!("📁 Reading {} bytes from hex string in source file: {}", bytes.len(), cyan(file_path));
                             return Ok(bytes);
                         },
                         Err(_) => {
@@ -234,25 +2819,68 @@ fn read_source_file(file_path: &str) -> Result<Vec<u8>, Box<dyn std::error::Erro
                         }
                     }
                 }
+                
+                // Odd-length hex is usually a truncated paste, so say so rather than silently reading text
+                if !hex_str.is_empty() && !hex_str.len().is_multiple_of(2) && hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+                    let message = format!("Source file looks like hex but has an odd number of digits ({})", hex_str.len());
+                    if strict {
+                        return Err(CrngError::Decode(message));
+                    }
+                    status!("{}", yellow(format!("⚠️  Warning: {}; reading it as raw text", message)));
+                }
+            }
+            
+            // Base64 is only considered when the text contains characters that can't be hex
+            if looks_like_base64(trimmed) && let Some(bytes) = decode_base64(trimmed) {
+                status!("📁 Reading {} bytes from base64 string in source file: {}", bytes.len(), cyan(file_path));
+                return Ok(bytes);
             }
             
-            // If not a valid hex string, treat the text content as raw bytes
+            // If not a valid hex or base64 string, treat the text content as raw bytes
             let bytes = content.as_bytes().to_vec();
-            println!("📁 Reading {} bytes from text file as raw bytes: \x1b[36m{}\x1b[0m", bytes.len(), file_path);
+            status!("📁 Reading {} bytes from text file as raw bytes: {}", bytes.len(), cyan(file_path));
             Ok(bytes)
         },
-        Err(_) => {
-            // If reading as text fails, read as binary
-            let bytes = fs::read(file_path)?;
-            println!("📁 Reading {} bytes from binary file: \x1b[36m{}\x1b[0m", bytes.len(), file_path);
+        Err(e) => {
+            // If the content isn't valid UTF-8, treat it as binary
+            let bytes = e.into_bytes();
+            status!("📁 Reading {} bytes from binary file: {}", bytes.len(), cyan(file_path));
             Ok(bytes)
         }
     }
 }
 
-fn parse_hex_string(hex_input: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+/// Shortens long input strings for display
+fn abbreviate(text: &str) -> String {
+    const MAX_DISPLAY_CHARS: usize = 64;
+    
+    if text.len() <= MAX_DISPLAY_CHARS {
+        text.to_string()
+    } else {
+        format!("{}... ({} characters)", &text[..MAX_DISPLAY_CHARS], format_number_with_commas(text.len() as u64))
+    }
+}
+
+fn parse_hex_string(hex_input: &str) -> Result<Vec<u8>, CrngError> {
     let trimmed = hex_input.trim();
     
+    // 0b and 0o select binary and octal digits instead of hex
+    let radix = match trimmed.get(..2) {
+        Some("0b" | "0B") => Some(("binary", 1, "Empty binary string")),
+        Some("0o" | "0O") => Some(("octal", 3, "Empty octal string")),
+        _ => None,
+    };
+    if let Some((base, bits_per_digit, empty)) = radix {
+        let digits = &trimmed[2..];
+        if digits.is_empty() {
+            return Err(CrngError::Empty(empty));
+        }
+        let bytes = decode_radix_digits(digits, bits_per_digit)
+            .ok_or_else(|| CrngError::Decode(format!("The {} string contains invalid digits", base)))?;
+        status!("🔤 Parsing {} bytes from {} string: {}", bytes.len(), base, cyan(abbreviate(digits)));
+        return Ok(bytes);
+    }
+    
     // Handle hex string (with or without 0x prefix)
     let hex_str = if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
         &trimmed[2..] // Remove 0x prefix
@@ -262,83 +2890,368 @@ fn parse_hex_string(hex_input: &str) -> Result<Vec<u8>, Box<dyn std::error::Erro
     
     // Validate hex string
     if hex_str.is_empty() {
-        return Err("Empty hex string".into());
+        return Err(CrngError::Empty("Empty hex string"));
+    }
+    
+    // Accept base64 pasted into --hex when it clearly isn't hex
+    if looks_like_base64(trimmed) && let Some(bytes) = decode_base64(trimmed) {
+        status!("🔤 Detected base64 input, parsing {} bytes: {}", bytes.len(), cyan(abbreviate(trimmed)));
+        return Ok(bytes);
     }
     
-    if hex_str.len() % 2 != 0 {
-        return Err("Hex string must have even length".into());
+    if !hex_str.len().is_multiple_of(2) {
+        return Err(CrngError::Decode("Hex string must have even length".to_string()));
     }
     
     if !hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err("Hex string contains invalid characters".into());
+        return Err(CrngError::Decode("Hex string contains invalid characters".to_string()));
     }
     
     // Decode hex string
     let bytes = hex::decode(hex_str)?;
-    println!("🔤 Parsing {} bytes from hex string: \x1b[36m{}\x1b[0m", bytes.len(), hex_str);
+    status!("🔤 Parsing {} bytes from hex string: {}", bytes.len(), cyan(abbreviate(hex_str)));
     Ok(bytes)
 }
 
-fn load_saved_quantum_bytes() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let hex_string = fs::read_to_string(DEFAULT_OUTPUT_FILE)?;
-    let bytes = hex::decode(hex_string.trim())?;
+fn parse_base64_string(base64_input: &str) -> Result<Vec<u8>, CrngError> {
+    let trimmed = base64_input.trim();
+    
+    if trimmed.is_empty() {
+        return Err(CrngError::Empty("Empty base64 string"));
+    }
+    
+    let bytes = decode_base64(trimmed).ok_or_else(|| CrngError::Decode("Invalid base64 string".to_string()))?;
+    if bytes.is_empty() {
+        return Err(CrngError::Empty("Base64 string decodes to no bytes"));
+    }
+    
+    status!("🔤 Parsing {} bytes from base64 string: {}", bytes.len(), cyan(abbreviate(trimmed)));
     Ok(bytes)
 }
 
-fn perform_multiple_flips(seed_bytes: &[u8], num_flips: usize) -> (u32, u32, u32, u32) {
-    // Generate N-1 flips using seeded CSRNG
-    let csrng_flips = num_flips - 1;
-    let csrng_bytes = csrng_flips * 1024;
+fn load_saved_quantum_bytes(encoding: Encoding) -> Result<Vec<u8>, CrngError> {
+    read_saved_bytes(&default_output_path(), encoding)
+}
+
+/// Reads entropy saved in `encoding`. The encoding is not guessed from the
+/// contents, since raw bytes can happen to be valid hex or base64 text; a file
+/// that doesn't decode, e.g. one saved under a different --encoding, is read as
+/// raw bytes.
+fn read_saved_bytes(path: &Path, encoding: Encoding) -> Result<Vec<u8>, CrngError> {
+    let data = read_maybe_compressed(path)?;
+    Ok(decode_saved_text(&data, encoding).unwrap_or(data))
+}
+
+/// Decodes hex or base64 text, one chunk per line when entropy has been appended
+/// with --append. Returns `None` for binary, or if any line fails to decode.
+fn decode_saved_text(data: &[u8], encoding: Encoding) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut bytes = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        bytes.extend(match encoding {
+            Encoding::Hex => hex::decode(line).ok()?,
+            Encoding::Base64 => decode_base64(line)?,
+            Encoding::Binary => return None,
+        });
+    }
+    Some(bytes)
+}
+
+/// Returns the marker file recording which saved entropy has been used, next to the saved file
+fn saved_marker_path() -> PathBuf {
+    let mut path = default_output_path().into_os_string();
+    path.push(".used");
+    PathBuf::from(path)
+}
+
+/// Returns true if the marker shows these saved bytes were already used by a run
+fn saved_entropy_was_used(bytes: &[u8]) -> bool {
+    fs::read_to_string(saved_marker_path()).is_ok_and(|marker| marker.trim() == hex::encode(Sha256::digest(bytes)))
+}
+
+/// Records that the saved bytes have been used. Only a digest is kept, so saving
+/// different entropy makes the file fresh again.
+fn mark_saved_entropy_used(bytes: &[u8]) {
+    if let Err(e) = fs::write(saved_marker_path(), hex::encode(Sha256::digest(bytes))) {
+        verbose!(1, "⚠️  Failed to mark saved entropy as used: {}", e);
+    }
+}
+
+/// Creates a seed from quantum bytes (we need exactly 32 bytes for the seeded generators).
+/// Entropy shorter than the seed is extended according to `pad`.
+fn derive_seed(seed_bytes: &[u8], pad: SeedPadding) -> [u8; SEED_LEN] {
+    let mut seed = [0u8; SEED_LEN];
+    if seed_bytes.len() >= SEED_LEN {
+        seed.copy_from_slice(&seed_bytes[..SEED_LEN]);
+        return seed;
+    }
     
-    if csrng_flips > 0 {
-        println!("⚡ Generating \x1b[36m{}\x1b[0m bytes from seeded CSRNG ({} flips)", csrng_bytes, csrng_flips);
+    match pad {
+        SeedPadding::Cycle => {
+            for (i, &byte) in seed_bytes.iter().cycle().take(SEED_LEN).enumerate() {
+                seed[i] = byte;
+            }
+        }
+        SeedPadding::Zero => seed[..seed_bytes.len()].copy_from_slice(seed_bytes),
+        SeedPadding::Hash => seed = Sha256::digest(seed_bytes).into(),
     }
+    seed
+}
+
+/// Parses a count with an optional `k` (thousand), `M` (million) or `G` (billion)
+/// suffix and `_` digit separators. Lowercase `m` and `g` are rejected as ambiguous.
+fn parse_count(value: &str) -> Result<usize, String> {
+    let digits = value.trim().replace('_', "");
+    let (number, multiplier) = match digits.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&digits[..i], 1_000),
+        Some((i, 'M')) => (&digits[..i], 1_000_000),
+        Some((i, 'G')) => (&digits[..i], 1_000_000_000),
+        Some((_, 'm' | 'g')) => return Err(format!("'{}' is ambiguous; use M for million or G for billion", value)),
+        _ => (digits.as_str(), 1),
+    };
     
-    // Create seed from quantum bytes (we need exactly 32 bytes for StdRng)
-    let mut seed = [0u8; 32];
-    if seed_bytes.len() >= 32 {
-        seed.copy_from_slice(&seed_bytes[..32]);
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("'{}' is not a whole number (suffixes k, M and G are allowed)", value));
+    }
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("'{}' is too large", value))
+}
+
+/// Parses a `--interval` value, a positive number of seconds
+fn parse_interval(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(secs),
+        Ok(_) => Err("must be a positive number of seconds".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parses a `--labels` value: the heads and tails names, separated by the first comma
+fn parse_labels(value: &str) -> Result<Labels, String> {
+    match value.split_once(',') {
+        Some((heads, tails)) if !heads.trim().is_empty() && !tails.trim().is_empty() => Ok(Labels {
+            heads: heads.trim().to_string(),
+            tails: tails.trim().to_string(),
+        }),
+        _ => Err("expected two comma-separated labels, e.g. Tea,Coffee".to_string()),
+    }
+}
+
+/// Parses and validates a `--prob` value, which must lie strictly between 0 and 1
+fn parse_probability(value: &str) -> Result<f64, String> {
+    let prob: f64 = value.parse().map_err(|_| format!("'{}' is not a number", value))?;
+    if prob > 0.0 && prob < 1.0 {
+        Ok(prob)
     } else {
-        // If we have fewer than 32 bytes, repeat the pattern
-        for (i, &byte) in seed_bytes.iter().cycle().take(32).enumerate() {
-            seed[i] = byte;
+        Err(format!("probability must be between 0 and 1 (exclusive), got {}", prob))
+    }
+}
+
+/// Performs `num_flips` weighted flips that land heads with probability `prob`.
+/// Each flip reads 7 bytes (56 bits, the top 53 of which are used) to form a
+/// uniform value in [0, 1), so no rejection is needed. Once the entropy bytes are
+/// used up, the remaining flips read from a CSRNG seeded by them.
+/// Returns (heads, tails, flips drawn directly from entropy).
+fn perform_weighted_flips(entropy: &[u8], num_flips: usize, prob: f64, csrng: Csrng, pad: SeedPadding) -> (usize, usize, usize) {
+    let direct_flips = std::cmp::min(entropy.len() / WEIGHTED_BYTES_PER_FLIP, num_flips);
+    let heads = weighted_flip_outcomes(entropy, num_flips, prob, csrng, pad).into_iter().filter(|&heads| heads).count();
+    
+    (heads, num_flips - heads, direct_flips)
+}
+
+/// Decides each weighted flip, true for heads. Flips draw 7 bytes of entropy each
+/// while it lasts, then continue from a CSRNG seeded with the entropy.
+fn weighted_flip_outcomes(entropy: &[u8], num_flips: usize, prob: f64, csrng: Csrng, pad: SeedPadding) -> Vec<bool> {
+    let direct_flips = std::cmp::min(entropy.len() / WEIGHTED_BYTES_PER_FLIP, num_flips);
+    let mut rng = csrng.seeded(derive_seed(entropy, pad));
+    
+    (0..num_flips)
+        .map(|flip| {
+            let mut buf = [0u8; 8];
+            if flip < direct_flips {
+                buf[1..].copy_from_slice(&entropy[flip * WEIGHTED_BYTES_PER_FLIP..(flip + 1) * WEIGHTED_BYTES_PER_FLIP]);
+            } else {
+                rng.fill_bytes(&mut buf[1..]);
+            }
+            
+            let uniform = (u64::from_be_bytes(buf) >> 3) as f64 / (1u64 << 53) as f64;
+            uniform < prob
+        })
+        .collect()
+}
+
+/// Decides `num_flips` flips from one bit each, true for a 1 bit. The entropy's
+/// bits are used first, most significant bit first, then the bits of a CSRNG
+/// seeded with the entropy.
+fn bit_flip_outcomes(entropy: &[u8], num_flips: usize, csrng: Csrng, pad: SeedPadding) -> Vec<bool> {
+    let mut bits = bytes_to_bits(entropy);
+    bits.truncate(num_flips);
+    if bits.len() < num_flips {
+        let mut extension = vec![0u8; (num_flips - bits.len()).div_ceil(8)];
+        csrng.seeded(derive_seed(entropy, pad)).fill_bytes(&mut extension);
+        bits.extend(bytes_to_bits(&extension));
+        bits.truncate(num_flips);
+    }
+    bits
+}
+
+/// Generates the `unit` CSRNG bytes that decide the CSRNG flip at `flip_index`
+fn csrng_flip_bytes(seed: &[u8; SEED_LEN], flip_index: usize, csrng: Csrng, unit: usize) -> Vec<u8> {
+    // Create a unique seed for each flip by combining original seed with flip index
+    let mut flip_seed = *seed;
+    let flip_bytes = flip_index.to_le_bytes();
+    for (i, &byte) in flip_bytes.iter().enumerate() {
+        if i < flip_seed.len() {
+            flip_seed[i] ^= byte; // XOR with flip index for uniqueness
         }
     }
     
+    // Create RNG for this flip
+    let mut rng = csrng.seeded(flip_seed);
+    let mut bytes = vec![0u8; unit];
+    timings::time(Phase::Csrng, || rng.fill_bytes(&mut bytes));
+    bytes
+}
+
+/// Returns the entropy bytes the direct flip counts: the first `flip_unit` bytes,
+/// or all of them when no --flip-unit was given or the entropy is shorter
+fn direct_flip_bytes(entropy: &[u8], flip_unit: Option<usize>) -> &[u8] {
+    &entropy[..direct_flip_bytes_len(entropy.len(), flip_unit)]
+}
+
+/// Returns how many of `entropy_len` bytes the direct flip counts
+fn direct_flip_bytes_len(entropy_len: usize, flip_unit: Option<usize>) -> usize {
+    flip_unit.map_or(entropy_len, |unit| unit.min(entropy_len))
+}
+
+/// Describes what the direct flip counts, for --explain
+fn direct_flip_scope(flip_unit: Option<usize>) -> String {
+    match flip_unit {
+        Some(unit) => format!("the first {} entropy bytes", format_number_with_commas(unit as u64)),
+        None => "all entropy bytes".to_string(),
+    }
+}
+
+/// 95% Wilson score interval for the heads probability after `heads` of `flips`
+/// flips. Unlike the Wald interval it stays within 0..1 and behaves at small counts.
+fn wilson_interval(heads: usize, flips: usize) -> (f64, f64) {
+    const Z: f64 = 1.96;
+    
+    if flips == 0 {
+        return (0.0, 1.0);
+    }
+    
+    let n = flips as f64;
+    let p = heads as f64 / n;
+    let denominator = 1.0 + Z * Z / n;
+    let center = (p + Z * Z / (2.0 * n)) / denominator;
+    let half_width = Z / denominator * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt();
+    
+    ((center - half_width).max(0.0), (center + half_width).min(1.0))
+}
+
+/// Decides the first `limit` flips individually, true for heads (more 1-bits, or a
+/// weighted heads). The CSRNG flips come first in index order and the direct entropy flip last.
+fn flip_outcomes(args: &Args, entropy_bytes: &[u8], limit: usize) -> Vec<bool> {
+    let limit = limit.min(args.num_flips);
+    
+    if let Some(prob) = args.prob {
+        return weighted_flip_outcomes(entropy_bytes, limit, prob, args.csrng, args.pad);
+    }
+    
+    if args.bits {
+        return bytes_to_bits(entropy_bytes).into_iter().take(limit).collect();
+    }
+    
+    if args.flips_per_byte {
+        return bit_flip_outcomes(entropy_bytes, limit, args.csrng, args.pad);
+    }
+    
+    if args.extract {
+        return extract_fair_bits(entropy_bytes, limit);
+    }
+    
+    let seed = derive_seed(entropy_bytes, args.pad);
+    let unit = args.flip_unit().unwrap_or(DEFAULT_FLIP_UNIT);
+    let mut outcomes: Vec<bool> = (0..limit.min(args.num_flips - 1))
+        .into_par_iter()
+        .map(|flip_index| {
+            let (ones, zeros) = count_bits(&csrng_flip_bytes(&seed, flip_index, args.csrng, unit));
+            ones > zeros
+        })
+        .collect();
+    if limit == args.num_flips {
+        let (ones, zeros) = count_bits(direct_flip_bytes(entropy_bytes, args.flip_unit()));
+        outcomes.push(ones > zeros);
+    }
+    outcomes
+}
+
+fn perform_multiple_flips(seed_bytes: &[u8], num_flips: usize, csrng: Csrng, pad: SeedPadding, flip_unit: Option<usize>, show_progress: bool) -> (u64, u64, u64, u64) {
+    // Generate N-1 flips using seeded CSRNG
+    let csrng_flips = num_flips - 1;
+    let unit = flip_unit.unwrap_or(DEFAULT_FLIP_UNIT);
+    let csrng_bytes = csrng_flips * unit;
+    
+    if csrng_flips > 0 {
+        status!("⚡ Generating {} bytes from seeded CSRNG ({} flips)", cyan(csrng_bytes), csrng_flips);
+    }
+    
+    let seed = derive_seed(seed_bytes, pad);
+    
     // Generate N-1 flips using parallel CSRNG
-    let (csrng_ones, csrng_zeros): (u32, u32) = if csrng_flips > 0 {
-        (0..csrng_flips)
-            .into_par_iter()
-            .map(|flip_index| {
-                // Create a unique seed for each flip by combining original seed with flip index
-                let mut flip_seed = seed;
-                let flip_bytes = flip_index.to_le_bytes();
-                for (i, &byte) in flip_bytes.iter().enumerate() {
-                    if i < flip_seed.len() {
-                        flip_seed[i] ^= byte; // XOR with flip index for uniqueness
-                    }
+    FLIPPING.store(true, Ordering::Relaxed);
+    let completed = AtomicUsize::new(0);
+    let done = AtomicBool::new(false);
+    let (csrng_ones, csrng_zeros): (u64, u64) = thread::scope(|scope| {
+        if show_progress && csrng_flips >= PROGRESS_MIN_FLIPS {
+            // Ticker thread redraws the bar while the parallel iterator bumps the counter
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    print!("{}", helpers::asciify(&format!("\r⏳ {}", progress_bar(completed.load(Ordering::Relaxed), csrng_flips, 30))));
+                    let _ = std::io::stdout().flush();
+                    thread::sleep(Duration::from_millis(100));
                 }
-                
-                // Create RNG for this flip
-                let mut rng = StdRng::from_seed(flip_seed);
-                let mut bytes = vec![0u8; 1024];
-                rng.fill_bytes(&mut bytes);
-                
-                // Count bits for this flip
-                count_bits(&bytes)
-            })
-            .reduce(|| (0, 0), |acc, (ones, zeros)| (acc.0 + ones, acc.1 + zeros))
-    } else {
-        (0, 0)
-    };
+                out!("\r⏳ {}", progress_bar(completed.load(Ordering::Relaxed), csrng_flips, 30));
+            });
+        }
+        
+        let totals = if csrng_flips > 0 {
+            (0..csrng_flips)
+                .into_par_iter()
+                .map(|flip_index| {
+                    // After Ctrl-C the remaining flips are skipped and left out of the tally
+                    if INTERRUPTED.load(Ordering::Relaxed) {
+                        return (0, 0);
+                    }
+                    let bytes = csrng_flip_bytes(&seed, flip_index, csrng, unit);
+                    completed.fetch_add(1, Ordering::Relaxed);
+                    
+                    // Count bits for this flip
+                    count_bits(&bytes)
+                })
+                .reduce(|| (0, 0), |acc, (ones, zeros)| (acc.0 + ones, acc.1 + zeros))
+        } else {
+            (0, 0)
+        };
+        done.store(true, Ordering::Relaxed);
+        totals
+    });
+    FLIPPING.store(false, Ordering::Relaxed);
     
-    if csrng_flips > 0 {
-        println!("✅ Generated \x1b[36m{}\x1b[0m bytes from CSRNG", csrng_bytes);
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        let flipped = completed.load(Ordering::Relaxed);
+        status!("{}", yellow(format!("⚠️  Interrupted after {} of {} CSRNG flips; the tally below is partial", format_number_with_commas(flipped as u64), format_number_with_commas(csrng_flips as u64))));
+    } else if csrng_flips > 0 {
+        status!("✅ Generated {} bytes from CSRNG", cyan(csrng_bytes));
     }
     
     // Generate the Nth (final) flip using quantum bytes directly
-    println!("🔬 Using quantum entropy directly for final flip");
-    let (quantum_ones, quantum_zeros) = count_bits(seed_bytes);
+    status!("🔬 Using the entropy directly for final flip");
+    let (quantum_ones, quantum_zeros) = count_bits(direct_flip_bytes(seed_bytes, flip_unit));
     
     // Combine results
     let total_ones = csrng_ones + quantum_ones;
@@ -347,132 +3260,540 @@ fn perform_multiple_flips(seed_bytes: &[u8], num_flips: usize) -> (u32, u32, u32
     (total_ones, total_zeros, quantum_ones, quantum_zeros)
 }
 
-fn fetch_random_bytes_with_source(num_bytes: usize) -> (Vec<u8>, bool) {
-    // Create a client with timeout settings
-    let client = Client::builder()
-        .timeout(Duration::from_secs(30))
-        .connect_timeout(Duration::from_secs(10))
-        .build()
-        .expect("Failed to create HTTP client");
+fn fetch_random_bytes_with_source(num_bytes: usize, args: &Args) -> (Vec<u8>, EntropyOrigin) {
+    timings::time(Phase::Fetch, || fetch_from_source_chain(num_bytes, args))
+}
+
+/// Creates the HTTP client for the quantum sources from the timeout and TLS settings
+fn build_client() -> Client {
+    qcoin::fetch::client(fetch_options()).expect("Failed to create HTTP client")
+}
+
+/// Builds the sources to try in order: the quantum providers (alone, raced or
+/// mixed), then saved entropy, then the CSRNG unless --quantum-only was given.
+/// With --offline only the saved entropy is left. --prefer-saved moves the saved
+/// entropy to the front, keeping it as the fallback too when --saved-max-age may skip it.
+fn source_chain(args: &Args) -> Vec<Box<dyn EntropySource>> {
+    let mut chain: Vec<Box<dyn EntropySource>> = Vec::new();
+    let saved = |max_age| Box::new(source::SavedFile { reuse: args.saved_reuse(), max_age, encoding: args.saved_encoding() });
     
-    // Try ANU QRNG first (cap at 1024 bytes due to API limitations)
-    let anu_bytes_to_fetch = std::cmp::min(num_bytes, 1024);
+    let max_age = args.saved_max_age.map(Duration::from_secs);
+    if args.prefer_saved {
+        chain.push(saved(max_age));
+    }
     
-    println!("🔍 \x1b[33mTrying ANU QRNG...\x1b[0m");
-    match fetch_anu_qrng_bytes(&client, anu_bytes_to_fetch) {
-        Ok(bytes) => {
-            println!("✅ ANU QRNG: Received \x1b[32m{} bytes\x1b[0m", bytes.len());
-            return (bytes, true); // True indicates quantum source
-        }
-        Err(e) => {
-            eprintln!("❌ ANU QRNG: \x1b[31m{}\x1b[0m", e);
-            println!("🔄 \x1b[33mTrying qrandom.io...\x1b[0m");
+    if !args.offline {
+        let client = build_client();
+        let networks: Vec<source::Network> = args
+            .sources
+            .iter()
+            .map(|&source| source::Network { source, client: client.clone() })
+            .collect();
+        
+        if args.mix {
+            chain.push(Box::new(source::Mix { sources: networks }));
+        } else if args.race {
+            chain.push(Box::new(source::Race { sources: networks }));
+        } else {
+            chain.extend(networks.into_iter().map(|network| Box::new(network) as Box<dyn EntropySource>));
         }
     }
     
-    // Fallback to qrandom.io
-    match fetch_qrandom_bytes(&client, num_bytes) {
-        Ok(bytes) => {
-            println!("✅ qrandom.io: Received \x1b[32m{} bytes\x1b[0m", bytes.len());
-            return (bytes, true); // True indicates quantum source
+    if !args.prefer_saved || max_age.is_some() {
+        chain.push(saved(None));
+    }
+    if !args.quantum_only && !args.offline {
+        chain.push(Box::new(source::Csrng));
+    }
+    chain
+}
+
+fn fetch_from_source_chain(num_bytes: usize, args: &Args) -> (Vec<u8>, EntropyOrigin) {
+    let window = args.session_cache.map(Duration::from_secs);
+    if let Some(window) = window && let Some((bytes, origin, age)) = session::load(num_bytes, window) {
+        status!(
+            "♻️  Reusing {} bytes from {} fetched {}s ago (--session-cache)",
+            bytes.len(),
+            origin.as_str(),
+            age.as_secs()
+        );
+        return (bytes, origin);
+    }
+    
+    let mut result = None;
+    for source in source_chain(args) {
+        let _attempt = tracing::info_span!("fetch", source = source.name(), bytes = num_bytes).entered();
+        if source.is_quantum() {
+            status!("🔍 {}", yellow(format!("Trying {}...", source.name())));
+        } else {
+            status!("🔄 {}", yellow(format!("Falling back to {}...", source.name())));
         }
-        Err(e) => {
-            eprintln!("❌ qrandom.io: \x1b[31m{}\x1b[0m", e);
-            println!("🔄 \x1b[33mFalling back to CSRNG...\x1b[0m");
+        let mut fetched = source.fetch_with_origin(num_bytes);
+        if let Some(action) = args.dedupe
+            && source.is_quantum()
+            && let Ok((bytes, _)) = &fetched
+            && is_repeat_fetch(bytes)
+        {
+            // Printed even when quiet, like the CSRNG fallback warning
+            err_out!(
+                "⚠️  {}",
+                bold_red(format!("{} served the same {} bytes as a recent fetch; they may be stale or cached", source.name(), bytes.len()))
+            );
+            if action == DedupeAction::Refetch {
+                status!("🔁 {}", yellow(format!("Fetching again from {}...", source.name())));
+                fetched = source.fetch_with_origin(num_bytes).and_then(|(bytes, origin)| {
+                    if is_repeat_fetch(&bytes) { Err(CrngError::Repeated) } else { Ok((bytes, origin)) }
+                });
+            }
+        }
+        match fetched {
+            Ok((bytes, origin)) if !source.is_quantum() => {
+                // Printed even when quiet, so fallback output is never mistaken for quantum output
+                err_out!(
+                    "⚠️  {}",
+                    bold_red(format!("THIS IS NOT QUANTUM ENTROPY: {} bytes came from the {} fallback", bytes.len(), source.name()))
+                );
+                result = Some((bytes, origin));
+                break;
+            }
+            Ok((bytes, origin)) => {
+                status!("✅ {}: Received {}", source.name(), green(format!("{} bytes", bytes.len())));
+                if window.is_some()
+                    && matches!(origin, EntropyOrigin::Anu | EntropyOrigin::Qrandom | EntropyOrigin::Mixed)
+                    && let Err(e) = session::store(num_bytes, &bytes, origin)
+                {
+                    verbose!(1, "⚠️  Failed to update the session cache: {}", e);
+                }
+                result = Some((bytes, origin));
+                break;
+            }
+            Err(e) => tracing::warn!(error = %e, "❌ {}", source.name()),
         }
     }
     
-    // Last resort: try to reuse saved quantum bytes
-    match load_saved_quantum_bytes() {
-        Ok(bytes) => {
-            println!("♻️  Reusing saved quantum entropy from file: \x1b[36m{}\x1b[0m", DEFAULT_OUTPUT_FILE);
-            return (bytes, true); // True since these are quantum bytes
+    // Sources still running in the background of a race are recorded by a later persist
+    if let Err(e) = stats::persist(&stats_path()) {
+        verbose!(1, "⚠️  Failed to update source statistics: {}", e);
+    }
+    if let Some(result) = result {
+        return result;
+    }
+    
+    if args.offline {
+        fail("--offline needs entropy from --source, --hex, --base64 or a saved entropy file");
+    } else if args.quantum_only {
+        fail("All quantum sources failed and --quantum-only forbids the CSRNG fallback");
+    }
+    fail("All entropy sources failed");
+}
+
+/// Checks fetched bytes against the --dedupe ring of recent fetches, treating
+/// them as fresh if the ring can't be read or written
+fn is_repeat_fetch(bytes: &[u8]) -> bool {
+    dedupe::is_repeat(bytes).unwrap_or_else(|e| {
+        verbose!(1, "⚠️  Failed to update the recent fetch hashes: {}", e);
+        false
+    })
+}
+
+/// Reads the root certificates in a PEM bundle, exiting if the file is unusable
+fn load_ca_bundle(path: &str) -> Vec<reqwest::Certificate> {
+    let certs = fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|pem| reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| e.to_string()));
+    
+    match certs {
+        Ok(certs) if !certs.is_empty() => {
+            verbose!(1, "🔐 Loaded {} root certificates from {}", certs.len(), path);
+            certs
+        }
+        Ok(_) => {
+            fail(format!("No certificates found in CA bundle '{}'", path));
         }
         Err(e) => {
-            eprintln!("❌ No saved entropy: \x1b[31m{}\x1b[0m", e);
+            fail(format!("Failed to load CA bundle '{}': {}", path, e));
         }
     }
+}
 
-    // Final fallback to cryptographic SRNG (not quantum)
-    match fetch_crypto_srng_bytes(num_bytes) {
-        Ok(bytes) => {
-            println!("✅ CSRNG: \x1b[32m{} bytes\x1b[0m", bytes.len());
-            return (bytes, false); // False indicates non-quantum source
-        }
-        Err(e) => {
-            eprintln!("❌ CSRNG: \x1b[31m{}\x1b[0m", e);
+/// Parses a SHA-256 certificate fingerprint like `ab12…` or `AB:12:…`, exiting if it is malformed
+fn parse_cert_pin(pin: &str) -> [u8; 32] {
+    let digits = pin.replace(':', "");
+    match hex::decode(&digits).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) {
+        Some(fingerprint) => fingerprint,
+        None => {
+            fail(format!("Invalid --pin-sha256 '{}': expected 64 hex digits", pin));
         }
     }
+}
+
+/// Clamps a --chunk-size to what the ANU backend at `anu_url` accepts: the public
+/// API's 1024-byte maximum, or anything for a custom backend
+fn anu_chunk_size(requested: usize, anu_url: &str) -> usize {
+    if requested > ANU_MAX_CHUNK && anu_url.trim_end_matches('/') == ANU_DEFAULT_URL {
+        status!(
+            "{}",
+            yellow(format!("⚠️  Warning: The public ANU API returns at most {} bytes per request, using that instead of {}", ANU_MAX_CHUNK, requested))
+        );
+        ANU_MAX_CHUNK
+    } else {
+        requested
+    }
+}
+
+/// Prints a provider's response metadata as `key=value` pairs when --show-metadata is set
+fn print_metadata(source: &str, metadata: &serde_json::Map<String, serde_json::Value>) {
+    if !helpers::show_metadata() {
+        return;
+    }
     
-    eprintln!("💥 All entropy sources failed");
-    std::process::exit(1);
+    if metadata.is_empty() {
+        out!("🏷️  {} metadata: {}", source, yellow("none provided"));
+        return;
+    }
+    
+    let fields: Vec<String> = metadata
+        .iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(text) => format!("{}={}", key, text),
+            other => format!("{}={}", key, other),
+        })
+        .collect();
+    out!("🏷️  {} metadata: {}", source, fields.join(", "));
 }
 
-fn fetch_qrandom_bytes(client: &Client, num_bytes: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let url = format!("https://qrandom.io/api/random/binary?bytes={}", num_bytes);
+fn fetch_crypto_srng_bytes(num_bytes: usize) -> Result<Vec<u8>, CrngError> {
+    let mut rng = rand::rng();
+    let mut bytes = vec![0u8; num_bytes];
+    
+    // Fill the vector with cryptographically secure random bytes
+    rng.fill_bytes(&mut bytes);
     
-    let response = client.get(url).send()?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
     
-    if !response.status().is_success() {
-        return Err(format!("HTTP {}", response.status()).into());
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("qcoin").chain(args.iter().copied()))
     }
     
-    let json_response: QRandomResponse = response.json()?;
+    #[test]
+    fn cli_definition_is_consistent() {
+        // Global flags that conflict with non-global ones only fail once a subcommand is parsed
+        Args::command().debug_assert();
+    }
     
-    let binary_response = client.get(&json_response.binary_url).send()?;
+    #[test]
+    fn offline_conflicts_with_network_only_modes() {
+        assert_eq!(parse(&["--offline", "--race"]).err().map(|e| e.kind()), Some(ErrorKind::ArgumentConflict));
+        assert_eq!(parse(&["--offline", "--repeat", "2", "--fresh-entropy"]).err().map(|e| e.kind()), Some(ErrorKind::ArgumentConflict));
+        assert!(parse(&["--offline", "--hex", "ab"]).is_ok());
+    }
     
-    if !binary_response.status().is_success() {
-        return Err(format!("Binary fetch HTTP {}", binary_response.status()).into());
+    #[test]
+    fn used_saved_entropy_is_refused_by_default_only_when_quantum_is_required() {
+        assert_eq!(parse(&[]).unwrap().saved_reuse(), SavedReuse::Warn);
+        assert_eq!(parse(&["--quantum-only"]).unwrap().saved_reuse(), SavedReuse::Refuse);
+        assert_eq!(parse(&["--strict"]).unwrap().saved_reuse(), SavedReuse::Refuse);
+        assert_eq!(parse(&["--quantum-only", "--saved-reuse", "allow"]).unwrap().saved_reuse(), SavedReuse::Allow);
     }
     
-    let bytes = binary_response.bytes()?.to_vec();
+    #[test]
+    fn json_result_has_a_stable_set_of_keys() {
+        let seed = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+        let args = parse(&["--hex", seed, "-n", "3", "--format", "json"]).unwrap();
+        let result = run_experiment(&args, hex::decode(seed).unwrap(), EntropyOrigin::Hex);
+        let json = serde_json::to_value(&result).unwrap();
+        
+        let keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(keys, ["entropy_bits_per_flip", "entropy_used", "ones", "outcome", "ratio", "schema_version", "source", "version", "yes", "zeros"]);
+        assert_eq!(json["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(json["source"], "hex");
+        assert_eq!(json["entropy_used"], 32);
+    }
     
-    Ok(bytes)
-}
-
-fn fetch_anu_qrng_bytes(client: &Client, num_bytes: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut all_bytes = Vec::new();
-    let mut remaining = num_bytes;
+    #[test]
+    fn labels_name_the_outcomes() {
+        let seed = "ffffffffffffffff";
+        let args = parse(&["--hex", seed, "--labels", "Tea, Coffee", "--format", "json"]).unwrap();
+        let json = serde_json::to_value(run_experiment(&args, hex::decode(seed).unwrap(), EntropyOrigin::Hex)).unwrap();
+        assert_eq!(json["yes"], true);
+        assert_eq!(json["outcome"], "YES");
+        assert_eq!(json["label"], "Tea");
+        assert_eq!(args.outcome_label(false), "Coffee");
+        
+        assert_eq!(parse(&[]).unwrap().outcome_label(true), "YES");
+        assert!(parse(&["--labels", "Tea"]).is_err());
+        assert!(parse(&["--labels", "Tea,"]).is_err());
+    }
     
-    // ANU QRNG has a maximum of 1024 elements per request
-    while remaining > 0 {
-        let chunk_size = std::cmp::min(remaining, 1024);
-        let url = format!("https://qrng.anu.edu.au/API/jsonI.php?length={}&type=uint8", chunk_size);
+    #[test]
+    fn flips_per_byte_uses_entropy_bits_before_the_csrng() {
+        let entropy = [0b1010_0000, 0xff];
+        let outcomes = bit_flip_outcomes(&entropy, 20, Csrng::Chacha20, SeedPadding::Hash);
+        assert_eq!(outcomes.len(), 20);
+        assert_eq!(outcomes[..16], bytes_to_bits(&entropy)[..]);
+        assert_eq!(outcomes, bit_flip_outcomes(&entropy, 20, Csrng::Chacha20, SeedPadding::Hash));
+        assert_eq!(bit_flip_outcomes(&entropy, 3, Csrng::Chacha20, SeedPadding::Hash), [true, false, true]);
         
-        let response = client.get(&url).send()?;
+        let args = parse(&["--hex", "a0ff", "-n", "20", "--flips-per-byte", "--csrng", "chacha20"]).unwrap();
+        assert_eq!(flip_outcomes(&args, &entropy, 20), outcomes);
+        assert!(parse(&["--flips-per-byte", "--bits"]).is_err());
+    }
+    
+    #[test]
+    fn extract_flips_read_the_extracted_bits() {
+        let entropy = [0xff; 64];
+        let args = parse(&["--hex", "ff", "-n", "300", "--extract"]).unwrap();
+        assert_eq!(flip_outcomes(&args, &entropy, 300), extract_fair_bits(&entropy, 300));
+        assert_eq!(flip_outcomes(&args, &entropy, 10), extract_fair_bits(&entropy, 10));
+        assert_eq!(entropy_used(&args, entropy.len()), 64);
+        assert_eq!(parse(&["--extract", "--flip-unit", "8"]).err().map(|e| e.kind()), Some(ErrorKind::ArgumentConflict));
+        assert_eq!(parse(&["--extract", "--prob", "0.3"]).err().map(|e| e.kind()), Some(ErrorKind::ArgumentConflict));
+    }
+    
+    #[test]
+    fn hex_out_saves_the_derived_seed() {
+        let path = std::env::temp_dir().join(format!("qcoin-seed-{}.hex", std::process::id()));
+        let entropy = "0123456789abcdef";
+        let args = parse(&["--hex", entropy, "-n", "3", "--pad", "cycle", "--hex-out", path.to_str().unwrap()]).unwrap();
+        run_experiment(&args, hex::decode(entropy).unwrap(), EntropyOrigin::Hex);
         
-        if !response.status().is_success() {
-            return Err(format!("HTTP {}", response.status()).into());
+        let saved = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(saved.trim(), entropy.repeat(4));
+    }
+    
+    #[test]
+    fn hex_out_is_not_written_when_the_seed_does_not_decide_the_flips() {
+        let path = std::env::temp_dir().join(format!("qcoin-no-seed-{}.hex", std::process::id()));
+        let entropy = "0123456789abcdef0123456789abcdef";
+        for mode in [&["-n", "3", "--bits"][..], &["-n", "3", "--extract"], &["-n", "3", "--flips-per-byte"], &["-n", "3", "--prob", "0.3"], &["-n", "1"]] {
+            let mut argv = vec!["--hex", entropy, "--hex-out", path.to_str().unwrap()];
+            argv.extend(mode);
+            let args = parse(&argv).unwrap();
+            run_experiment(&args, hex::decode(entropy).unwrap(), EntropyOrigin::Hex);
+            assert!(!path.exists(), "{:?} wrote a seed", mode);
         }
+    }
+    
+    #[test]
+    fn int_bounds_cover_the_i64_and_u64_ranges() {
+        assert_eq!(parse_int_bound("-9223372036854775808"), Ok(i64::MIN as i128));
+        assert_eq!(parse_int_bound("18_446_744_073_709_551_615"), Ok(u64::MAX as i128));
+        assert!(parse_int_bound("18446744073709551616").is_err());
+        assert!(parse_int_bound("1.5").is_err());
         
-        let anu_response: AnuQrngResponse = response.json()?;
+        let args = parse(&["int", "--min", "-5", "--max", "5"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Int { min: -5, max: 5 })));
+    }
+    
+    #[test]
+    fn choose_needs_at_least_one_item() {
+        assert!(parse(&["choose"]).is_err());
+        let args = parse(&["choose", "tea", "coffee"]).unwrap();
+        assert!(matches!(&args.command, Some(Command::Choose { items }) if items == &["tea", "coffee"]));
+    }
+    
+    #[test]
+    fn entropy_used_counts_each_byte_that_decides_a_flip_once() {
+        let used = |flags: &[&str], entropy_len| entropy_used(&parse(flags).unwrap(), entropy_len);
+        assert_eq!(used(&["-n", "1000"], 1024), 1024);
+        assert_eq!(used(&["-n", "1000", "--flip-unit", "8"], 1024), SEED_LEN);
+        assert_eq!(used(&["-n", "1", "--flip-unit", "8"], 1024), 8);
+        assert_eq!(used(&["-n", "20", "--bits"], 3), 3);
+        assert_eq!(used(&["-n", "10", "--flips-per-byte"], 1024), 2);
+        assert_eq!(used(&["-n", "100000", "--flips-per-byte"], 1024), 1024);
+        assert_eq!(used(&["-n", "2", "--prob", "0.3"], 1024), 14);
+        assert_eq!(used(&["-n", "1000", "--prob", "0.3"], 70), 70);
+    }
+    
+    #[test]
+    fn counts_accept_suffixes_and_separators() {
+        assert_eq!(parse_count("42"), Ok(42));
+        assert_eq!(parse_count("10k"), Ok(10_000));
+        assert_eq!(parse_count("1M"), Ok(1_000_000));
+        assert_eq!(parse_count("2G"), Ok(2_000_000_000));
+        assert_eq!(parse_count("1_000_000"), Ok(1_000_000));
+        assert!(parse_count("1m").is_err());
+        assert!(parse_count("1.5k").is_err());
+        assert!(parse_count("k").is_err());
+        assert!(parse_count("-5").is_err());
+        assert!(parse_count("99999999999999999999G").is_err());
+    }
+    
+    #[test]
+    fn wilson_interval_matches_known_values() {
+        let (low, high) = wilson_interval(50, 100);
+        assert!((low - 0.4038).abs() < 1e-4 && (high - 0.5962).abs() < 1e-4);
         
-        if !anu_response.success {
-            return Err("API returned success=false".into());
-        }
+        let (low, high) = wilson_interval(0, 10);
+        assert_eq!(low, 0.0);
+        assert!((high - 0.2775).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn default_output_is_used_when_o_is_omitted() {
+        let args = parse(&[]).unwrap();
+        assert!(args.output_file.is_empty());
+        assert_eq!(args.output_paths().len(), 1);
+        assert_eq!(Path::new(&args.output_paths()[0]), default_output_path());
+    }
+    
+    #[test]
+    fn explicit_output_is_used() {
+        let args = parse(&["-o", "saved.hex"]).unwrap();
+        assert_eq!(args.output_paths(), ["saved.hex"]);
+    }
+    
+    #[test]
+    fn each_output_is_encoded_by_its_extension() {
+        let args = parse(&["-o", "a.bin", "-o", "b.txt", "-o", "c.b64.gz", "-o", "d.bytes"]).unwrap();
+        let encodings: Vec<Encoding> = args.output_paths().iter().map(|path| args.save_options(path).encoding).collect();
+        assert_eq!(encodings, [Encoding::Binary, Encoding::Hex, Encoding::Base64, Encoding::Hex]);
         
-        if anu_response.data.len() != chunk_size {
-            return Err(format!("Expected {} bytes, got {}", chunk_size, anu_response.data.len()).into());
+        let args = parse(&["-o", "a.bin", "--encoding", "base64"]).unwrap();
+        assert_eq!(args.save_options("a.bin").encoding, Encoding::Base64);
+    }
+    
+    #[test]
+    fn source_coexists_with_default_output() {
+        let args = parse(&["-s", "entropy.hex"]).unwrap();
+        assert_eq!(args.source_file, ["entropy.hex"]);
+        assert_eq!(Path::new(&args.output_paths()[0]), default_output_path());
+    }
+    
+    #[test]
+    fn repeated_sources_are_kept_in_order() {
+        let args = parse(&["-s", "a.bin", "--source", "b.hex"]).unwrap();
+        assert_eq!(args.source_file, ["a.bin", "b.hex"]);
+    }
+    
+    #[test]
+    fn no_delay_only_unthrottles_a_custom_anu_url() {
+        assert!(!parse(&["--no-delay"]).unwrap().anu_unthrottled());
+        assert!(!parse(&["--no-delay", "--anu-url", "https://qrng.anu.edu.au/"]).unwrap().anu_unthrottled());
+        assert!(parse(&["--no-delay", "--anu-url", "http://localhost:8080"]).unwrap().anu_unthrottled());
+        assert!(!parse(&["--anu-url", "http://localhost:8080"]).unwrap().anu_unthrottled());
+        assert_eq!(parse(&["--no-delay", "--rate-limit", "60"]).err().map(|e| e.kind()), Some(ErrorKind::ArgumentConflict));
+    }
+    
+    #[test]
+    fn source_fallback_requires_a_source() {
+        assert_eq!(parse(&["--source-fallback"]).err().map(|e| e.kind()), Some(ErrorKind::MissingRequiredArgument));
+        assert!(parse(&["-s", "entropy.hex", "--source-fallback"]).unwrap().source_fallback);
+    }
+    
+    #[test]
+    fn raw_source_files_are_not_decoded() {
+        let path = std::env::temp_dir().join(format!("qcoin-raw-{}.txt", std::process::id()));
+        fs::write(&path, "abcd").unwrap();
+        let file = path.to_str().unwrap();
+        assert_eq!(read_source_file(file, false, false).unwrap(), [0xab, 0xcd]);
+        assert_eq!(read_source_file(file, false, true).unwrap(), b"abcd");
+        let _ = fs::remove_file(&path);
+    }
+    
+    #[test]
+    fn saved_files_decode_in_the_encoding_they_were_written_in() {
+        // Raw bytes that are also valid hex, which guessing from the contents would decode
+        let payload = b"0123abcd";
+        let dir = std::env::temp_dir().join(format!("qcoin-saved-{}", std::process::id()));
+        for (encoding, name) in [(Encoding::Binary, "saved.bin"), (Encoding::Hex, "saved.hex"), (Encoding::Base64, "saved.b64")] {
+            let path = dir.join(name);
+            let file = path.to_str().unwrap();
+            assert!(save_quantum_bytes_to_file(payload, file, SaveOptions { encoding, compression: None, append: false }));
+            assert_eq!(Encoding::from_path(file), Some(encoding));
+            assert_eq!(read_saved_bytes(&path, encoding).unwrap(), payload);
+            assert_eq!(read_source_file(file, false, false).unwrap(), payload);
         }
         
-        all_bytes.extend(anu_response.data);
-        remaining -= chunk_size;
-        
-        // Small delay between requests to be respectful to the API
-        if remaining > 0 {
-            std::thread::sleep(Duration::from_millis(100));
-        }
+        // Text that doesn't decode in the expected encoding falls back to raw bytes
+        let path = dir.join("saved.bytes");
+        fs::write(&path, "not hex!").unwrap();
+        assert_eq!(read_saved_bytes(&path, Encoding::Hex).unwrap(), b"not hex!");
+        let _ = fs::remove_dir_all(&dir);
     }
     
-    Ok(all_bytes)
-}
-
-fn fetch_crypto_srng_bytes(num_bytes: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut rng = rand::rng();
-    let mut bytes = vec![0u8; num_bytes];
+    #[test]
+    fn source_conflicts_with_explicit_output() {
+        let err = parse(&["-s", "entropy.hex", "-o", "saved.hex"]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+    }
     
-    // Fill the vector with cryptographically secure random bytes
-    rng.fill_bytes(&mut bytes);
+    #[test]
+    fn hex_coexists_with_default_and_explicit_output() {
+        // Hex string entropy is saved to the output file for reuse
+        assert!(parse(&["--hex", "ff"]).is_ok());
+        let args = parse(&["--hex", "ff", "-o", "saved.hex"]).unwrap();
+        assert_eq!(args.output_paths(), ["saved.hex"]);
+    }
     
-    Ok(bytes)
-}
\ No newline at end of file
+    #[test]
+    fn no_save_conflicts_with_explicit_output() {
+        assert!(parse(&["--no-save"]).is_ok());
+        let err = parse(&["--no-save", "-o", "saved.hex"]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+    }
+    
+    #[test]
+    fn seeded_flips_are_deterministic() {
+        let pattern: Vec<u8> = (0..1024u32).map(|i| (i * 7 + 3) as u8).collect();
+        assert_eq!(perform_multiple_flips(&pattern, 100, Csrng::default(), SeedPadding::Cycle, None, false), (409186, 410014, 4096, 4096));
+        // Five bytes are cycled to fill the 32-byte seed
+        assert_eq!(perform_multiple_flips(&pattern[..5], 10, Csrng::default(), SeedPadding::Cycle, None, false), (36804, 36964, 13, 27));
+        assert_eq!(perform_multiple_flips(&pattern[..5], 10, Csrng::default(), SeedPadding::Hash, None, false), (36917, 36851, 13, 27));
+    }
+    
+    #[test]
+    fn seeded_flip_totals_do_not_depend_on_the_thread_count() {
+        let pattern: Vec<u8> = (0..64u8).collect();
+        let flip = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| perform_multiple_flips(&pattern, 500, Csrng::default(), SeedPadding::Hash, None, false))
+        };
+        assert_eq!(flip(1), flip(4));
+    }
+    
+    #[test]
+    fn tallies_past_u32_max_bits_are_not_wrapped() {
+        // 513 flips of 1 MiB are 2^32 + 2^23 bits, just past what a u32 tally can hold
+        let unit = 1 << 20;
+        let entropy = vec![0xffu8; unit];
+        let (ones, zeros, q_ones, q_zeros) = perform_multiple_flips(&entropy, 513, Csrng::Xoshiro, SeedPadding::Hash, Some(unit), false);
+        assert_eq!(ones + zeros, 513 * unit as u64 * 8);
+        assert!((ones as f64 / (ones + zeros) as f64 - 0.5).abs() < 0.01);
+        assert_eq!((q_ones, q_zeros), (unit as u64 * 8, 0));
+    }
+    
+    #[test]
+    fn flip_unit_sets_the_bytes_counted_per_flip() {
+        let entropy: Vec<u8> = (0..64u8).collect();
+        let (ones, zeros, q_ones, q_zeros) = perform_multiple_flips(&entropy, 3, Csrng::default(), SeedPadding::Hash, Some(16), false);
+        assert_eq!(ones + zeros, 3 * 16 * 8);
+        assert_eq!((q_ones, q_zeros), count_bits(&entropy[..16]));
+        
+        let (ones, zeros, q_ones, q_zeros) = perform_multiple_flips(&entropy, 3, Csrng::default(), SeedPadding::Hash, None, false);
+        assert_eq!(ones + zeros, 2 * 1024 * 8 + 64 * 8);
+        assert_eq!((q_ones, q_zeros), count_bits(&entropy));
+        
+        assert!(parse(&["--flip-unit", "0"]).is_err());
+        assert_eq!(parse(&["--flip-unit", "8", "--bits"]).err().map(|e| e.kind()), Some(ErrorKind::ArgumentConflict));
+    }
+    
+    #[test]
+    fn entropy_inputs_are_mutually_exclusive() {
+        for combination in [
+            &["--hex", "ff", "-s", "entropy.hex"][..],
+            &["--base64", "q8Ej", "-s", "entropy.hex"][..],
+            &["--hex", "ff", "--base64", "q8Ej"][..],
+        ] {
+            let err = parse(combination).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+        }
+    }
+}
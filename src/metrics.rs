@@ -0,0 +1,64 @@
+//! Prometheus text-format metrics for `--metrics`
+//!
+//! The metric names and the `source` label are part of the output format and
+//! must stay stable so scrape configs and dashboards keep working.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+
+/// Totals accumulated over the runs of one invocation
+#[derive(Default)]
+pub struct RunTotals {
+    pub flips: u64,
+    pub ones: u64,
+    pub zeros: u64,
+    /// Whether each source that provided entropy is quantum, keyed by source id
+    pub sources: BTreeMap<&'static str, bool>,
+}
+
+impl RunTotals {
+    pub fn add(&mut self, flips: u64, ones: u64, zeros: u64, source: &'static str, quantum: bool) {
+        self.flips += flips;
+        self.ones += ones;
+        self.zeros += zeros;
+        self.sources.insert(source, quantum);
+    }
+}
+
+/// Renders the totals and the time spent fetching entropy in the Prometheus text format
+pub fn render(totals: &RunTotals, fetch: Duration) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+        for (labels, value) in samples {
+            out.push_str(&format!("{}{} {}\n", name, labels, value));
+        }
+    };
+    
+    metric("qcoin_ones_total", "counter", "One bits counted across all flips.", vec![(String::new(), totals.ones.to_string())]);
+    metric("qcoin_zeros_total", "counter", "Zero bits counted across all flips.", vec![(String::new(), totals.zeros.to_string())]);
+    metric("qcoin_flips_total", "counter", "Coin flips performed.", vec![(String::new(), totals.flips.to_string())]);
+    metric(
+        "qcoin_source_quantum",
+        "gauge",
+        "Whether the entropy from each source used was quantum (1) or not (0).",
+        totals
+            .sources
+            .iter()
+            .map(|(source, &quantum)| (format!("{{source=\"{}\"}}", source), (quantum as u8).to_string()))
+            .collect(),
+    );
+    metric(
+        "qcoin_fetch_duration_seconds",
+        "gauge",
+        "Time spent fetching entropy.",
+        vec![(String::new(), format!("{:.6}", fetch.as_secs_f64()))],
+    );
+    out
+}
+
+/// Writes the metrics file, replacing any earlier one
+pub fn write(path: &str, totals: &RunTotals, fetch: Duration) -> std::io::Result<()> {
+    fs::write(path, render(totals, fetch))
+}
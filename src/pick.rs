@@ -0,0 +1,134 @@
+//! Unbiased integers drawn from entropy
+//!
+//! Each draw reads just enough bytes for the range, most significant byte
+//! first, and masks off the bits above it. A candidate outside the range is
+//! rejected and drawn again rather than reduced modulo the range, which would
+//! favour the smaller values. Once the entropy is used up, draws continue from
+//! a CSRNG seeded with it.
+
+use rand::RngCore;
+
+pub struct Picker {
+    entropy: Vec<u8>,
+    used: usize,
+    csrng: Box<dyn RngCore + Send>,
+    csrng_bytes: usize,
+}
+
+impl Picker {
+    /// Draws from `entropy`, then from `csrng` once it runs out
+    pub fn new(entropy: Vec<u8>, csrng: Box<dyn RngCore + Send>) -> Self {
+        Picker { entropy, used: 0, csrng, csrng_bytes: 0 }
+    }
+    
+    /// Returns a uniform integer in `0..bound`. `bound` must not be zero.
+    pub fn below(&mut self, bound: u128) -> u128 {
+        assert!(bound > 0, "bound must not be zero");
+        if bound == 1 {
+            return 0;
+        }
+        
+        let bits = 128 - (bound - 1).leading_zeros();
+        let len = bits.div_ceil(8) as usize;
+        let mask = u128::MAX >> (128 - bits);
+        loop {
+            let mut buf = [0u8; 16];
+            self.fill(&mut buf[16 - len..]);
+            let candidate = u128::from_be_bytes(buf) & mask;
+            if candidate < bound {
+                return candidate;
+            }
+        }
+    }
+    
+    /// Returns a uniform integer in `min..=max`. The range may span at most 2^127 values.
+    pub fn between(&mut self, min: i128, max: i128) -> i128 {
+        min + self.below((max - min) as u128 + 1) as i128
+    }
+    
+    /// Returns a uniform permutation of `0..len` from a Fisher–Yates shuffle
+    pub fn permutation(&mut self, len: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..len).collect();
+        for i in (1..len).rev() {
+            let j = self.below(i as u128 + 1) as usize;
+            indices.swap(i, j);
+        }
+        indices
+    }
+    
+    /// Returns the bytes read so far from the entropy and from the CSRNG
+    pub fn consumed(&self) -> (usize, usize) {
+        (self.used, self.csrng_bytes)
+    }
+    
+    fn fill(&mut self, buf: &mut [u8]) {
+        let direct = buf.len().min(self.entropy.len() - self.used);
+        buf[..direct].copy_from_slice(&self.entropy[self.used..self.used + direct]);
+        self.used += direct;
+        
+        if direct < buf.len() {
+            self.csrng.fill_bytes(&mut buf[direct..]);
+            self.csrng_bytes += buf.len() - direct;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    
+    fn picker_over(entropy: &[u8]) -> Picker {
+        Picker::new(entropy.to_vec(), Box::new(ChaCha20Rng::from_seed([7; 32])))
+    }
+    
+    #[test]
+    fn out_of_range_candidates_are_rejected_not_reduced() {
+        // 0..6 needs 3 bits: 7 and 6 are rejected, 5 is kept
+        let mut picker = picker_over(&[0x07, 0x06, 0x05]);
+        assert_eq!(picker.below(6), 5);
+        assert_eq!(picker.consumed(), (3, 0));
+    }
+    
+    #[test]
+    fn draws_continue_from_the_csrng_once_the_entropy_runs_out() {
+        let mut picker = picker_over(&[0x01]);
+        assert_eq!(picker.below(256), 1);
+        picker.below(1 << 16);
+        assert_eq!(picker.consumed(), (1, 2));
+    }
+    
+    #[test]
+    fn full_ranges_are_reachable() {
+        let mut picker = picker_over(&[0x01, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(picker.between(i64::MIN as i128, u64::MAX as i128), u64::MAX as i128);
+        assert_eq!(picker.between(5, 5), 5);
+        
+        let mut picker = picker_over(&[0x00; 8]);
+        assert_eq!(picker.between(i64::MIN as i128, i64::MAX as i128), i64::MIN as i128);
+    }
+    
+    #[test]
+    fn permutations_are_uniform() {
+        let mut picker = picker_over(&[]);
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..60_000 {
+            *counts.entry(picker.permutation(3)).or_insert(0u32) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        assert!(counts.values().all(|&count| (9_500..10_500).contains(&count)), "{:?}", counts);
+        assert_eq!(picker.permutation(1), [0]);
+        assert!(picker.permutation(0).is_empty());
+    }
+    
+    #[test]
+    fn every_value_is_equally_likely() {
+        let mut picker = picker_over(&[]);
+        let mut counts = [0u32; 6];
+        for _ in 0..60_000 {
+            counts[picker.below(6) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| (9_500..10_500).contains(&count)), "{:?}", counts);
+    }
+}
@@ -0,0 +1,148 @@
+//! A reservoir of entropy that is drawn down by runs and refilled in the background
+//!
+//! Consumers call [`EntropyPool::take`] while a refiller thread, running
+//! [`EntropyPool::run_refiller`], tops the pool back up whenever it drops below
+//! its low-water mark, so network latency overlaps with flipping.
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+
+use crate::EntropyOrigin;
+
+struct PoolState {
+    /// Refilled chunks in arrival order, each with where it came from
+    segments: VecDeque<(Vec<u8>, EntropyOrigin)>,
+    level: usize,
+    closed: bool,
+}
+
+pub struct EntropyPool {
+    state: Mutex<PoolState>,
+    changed: Condvar,
+    capacity: usize,
+    low_water: usize,
+}
+
+impl EntropyPool {
+    /// Creates an empty pool that refills up to `capacity` bytes whenever it holds
+    /// fewer than `low_water`
+    pub fn new(capacity: usize, low_water: usize) -> Self {
+        EntropyPool {
+            state: Mutex::new(PoolState {
+                segments: VecDeque::new(),
+                level: 0,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            capacity,
+            low_water,
+        }
+    }
+    
+    /// Takes `n` bytes, blocking until they are available. Returns `None` if the pool
+    /// is closed first, or for `n == 0`, which has no bytes to take an origin from.
+    /// The origin is shared by all the bytes, or is the first non-quantum origin
+    /// among them so the result never overstates its quality.
+    pub fn take(&self, n: usize) -> Option<(Vec<u8>, EntropyOrigin)> {
+        let mut state = self.lock();
+        while state.level < n && !state.closed {
+            state = self.changed.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        self.take_locked(&mut state, n)
+    }
+    
+    /// Like [`EntropyPool::take`], but returns `None` at once if the pool holds fewer than `n` bytes
+    pub fn try_take(&self, n: usize) -> Option<(Vec<u8>, EntropyOrigin)> {
+        self.take_locked(&mut self.lock(), n)
+    }
+    
+    fn take_locked(&self, state: &mut PoolState, n: usize) -> Option<(Vec<u8>, EntropyOrigin)> {
+        if n == 0 || state.level < n {
+            return None;
+        }
+        
+        let mut bytes = Vec::with_capacity(n);
+        let mut origins = Vec::new();
+        while bytes.len() < n {
+            let (segment, origin) = state.segments.front_mut()?;
+            let count = (n - bytes.len()).min(segment.len());
+            bytes.extend(segment.drain(..count));
+            origins.push(*origin);
+            if segment.is_empty() {
+                state.segments.pop_front();
+            }
+        }
+        state.level -= n;
+        
+        // Wake the refiller if this dropped the pool below its low-water mark
+        self.changed.notify_all();
+        
+        let origin = origins.iter().copied().find(|origin| !origin.is_quantum()).unwrap_or(origins[0]);
+        Some((bytes, origin))
+    }
+    
+    /// Refills the pool with `refill(needed)` whenever it runs low, until closed.
+    /// An empty refill closes the pool, since no more entropy is coming.
+    pub fn run_refiller(&self, mut refill: impl FnMut(usize) -> (Vec<u8>, EntropyOrigin)) {
+        loop {
+            let needed = {
+                let mut state = self.lock();
+                while !state.closed && state.level >= self.low_water {
+                    state = self.changed.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+                if state.closed {
+                    return;
+                }
+                self.capacity - state.level
+            };
+            
+            // Fetch without holding the lock so takers can keep draining the pool
+            let (bytes, origin) = refill(needed);
+            
+            let mut state = self.lock();
+            if bytes.is_empty() {
+                state.closed = true;
+            } else {
+                state.level += bytes.len();
+                state.segments.push_back((bytes, origin));
+            }
+            self.changed.notify_all();
+        }
+    }
+    
+    /// Stops the refiller and wakes any blocked takers
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.changed.notify_all();
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    
+    #[test]
+    fn zero_length_takes_return_none_without_panicking() {
+        let pool = EntropyPool::new(8, 1);
+        assert!(pool.take(0).is_none());
+        
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut refills = 0;
+                pool.run_refiller(|needed| {
+                    refills += 1;
+                    (if refills == 1 { vec![0xab; needed] } else { Vec::new() }, EntropyOrigin::Anu)
+                })
+            });
+            
+            assert!(pool.take(0).is_none());
+            assert_eq!(pool.take(8), Some((vec![0xab; 8], EntropyOrigin::Anu)));
+        });
+        assert!(pool.take(0).is_none());
+    }
+}
@@ -0,0 +1,64 @@
+//! Token-bucket rate limiting for ANU QRNG fetches
+//!
+//! [`FetchOptions`](crate::FetchOptions) holds its limiter behind an `Arc`, so
+//! every clone of the options shares one bucket: the `qcoin` binary keeps a
+//! single set of options for the whole process, which keeps `--repeat` runs and
+//! streams together under the configured rate. A fetch takes one token however
+//! many chunk requests it is split into, so large fetches keep their concurrency.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Requests that may be issued back to back before the rate applies
+const BURST: f64 = 3.0;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Paces requests to a given number per minute, after an initial burst
+#[derive(Debug)]
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+    per_sec: f64,
+    capacity: f64,
+}
+
+impl RateLimiter {
+    /// A limiter allowing `rpm` requests per minute
+    pub fn per_minute(rpm: u32) -> Self {
+        let capacity = BURST.min(rpm as f64);
+        RateLimiter {
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+            per_sec: rpm as f64 / 60.0,
+            capacity,
+        }
+    }
+    
+    /// Blocks until a request may be issued without exceeding the rate
+    pub fn wait(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                let now = Instant::now();
+                bucket.tokens = (bucket.tokens + now.duration_since(bucket.last_refill).as_secs_f64() * self.per_sec).min(self.capacity);
+                bucket.last_refill = now;
+                
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec)
+            };
+            
+            tracing::debug!("🚦 ANU rate limit reached, waiting {} ms", wait.as_millis());
+            thread::sleep(wait);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Short-lived cache that lets back-to-back invocations share fetched entropy
+//!
+//! With `--session-cache <secs>`, each fetch from a quantum provider is kept in a
+//! file keyed by the requested byte count. A later run asking for the same number
+//! of bytes within the window takes them from the file instead of the network.
+//! Only the first fetch of a process consults the cache, so runs within one
+//! invocation, like `--repeat --fresh-entropy`, never share entropy.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crate::{EntropyOrigin, default_output_path};
+
+/// Set once this process has fetched, after which the cache is only written
+static FETCHED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    origin: EntropyOrigin,
+    /// The entropy, hex-encoded
+    entropy: String,
+}
+
+/// Returns the cache file for fetches of `num_bytes`, next to the saved entropy file
+fn path(num_bytes: usize) -> PathBuf {
+    default_output_path().with_file_name(format!("session-{}.json", num_bytes))
+}
+
+/// Returns the cached entropy for `num_bytes` with its origin and age, if it was
+/// stored less than `window` ago
+pub fn load(num_bytes: usize, window: Duration) -> Option<(Vec<u8>, EntropyOrigin, Duration)> {
+    if FETCHED.swap(true, Ordering::Relaxed) {
+        return None;
+    }
+    
+    let path = path(num_bytes);
+    let age = SystemTime::now().duration_since(fs::metadata(&path).ok()?.modified().ok()?).unwrap_or_default();
+    if age >= window {
+        return None;
+    }
+    
+    let entry: Entry = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+    let bytes = hex::decode(entry.entropy).ok()?;
+    (bytes.len() == num_bytes).then_some((bytes, entry.origin, age))
+}
+
+/// Stores freshly fetched entropy for later runs requesting `num_bytes`
+pub fn store(num_bytes: usize, bytes: &[u8], origin: EntropyOrigin) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path(num_bytes);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let entry = Entry { origin, entropy: hex::encode(bytes) };
+    fs::write(path, serde_json::to_vec(&entry)?)?;
+    Ok(())
+}
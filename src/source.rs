@@ -0,0 +1,231 @@
+//! Entropy sources tried in order by the fetch chain
+//!
+//! Each link of the chain implements [`EntropySource`]. The chain is built from
+//! the command line by `source_chain` in `main.rs` and the first source that
+//! succeeds provides the entropy.
+
+use reqwest::blocking::Client;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::helpers::{self, cyan, green, xor_mix, yellow};
+use qcoin::CrngError;
+use crate::{
+    Encoding, EntropyOrigin, QuantumSource, SavedReuse, default_output_path, fetch_crypto_srng_bytes, fetch_options, load_saved_quantum_bytes,
+    mark_saved_entropy_used, saved_entropy_was_used,
+};
+
+pub trait EntropySource {
+    /// Name shown in status lines
+    fn name(&self) -> &str;
+    
+    /// Fetches `n` bytes of entropy. Sources that can't produce an exact amount,
+    /// like the saved file, return what they have.
+    fn fetch(&self, n: usize) -> Result<Vec<u8>, CrngError>;
+    
+    /// Whether the entropy is quantum, which decides whether it is saved for reuse
+    fn is_quantum(&self) -> bool;
+    
+    /// Origin reported for entropy from this source
+    fn origin(&self) -> EntropyOrigin;
+    
+    /// Fetches `n` bytes along with their origin. Sources combining several
+    /// providers override this to report which one delivered.
+    fn fetch_with_origin(&self, n: usize) -> Result<(Vec<u8>, EntropyOrigin), CrngError> {
+        self.fetch(n).map(|bytes| (bytes, self.origin()))
+    }
+}
+
+/// A quantum provider reached over HTTP
+pub struct Network {
+    pub source: QuantumSource,
+    pub client: Client,
+}
+
+impl EntropySource for Network {
+    fn name(&self) -> &str {
+        self.source.name()
+    }
+    
+    fn fetch(&self, n: usize) -> Result<Vec<u8>, CrngError> {
+        self.source.fetch(&self.client, n, fetch_options())
+    }
+    
+    fn is_quantum(&self) -> bool {
+        true
+    }
+    
+    fn origin(&self) -> EntropyOrigin {
+        self.source.into()
+    }
+}
+
+/// Queries every provider concurrently; the first successful response wins
+pub struct Race {
+    pub sources: Vec<Network>,
+}
+
+impl EntropySource for Race {
+    fn name(&self) -> &str {
+        "quantum source race"
+    }
+    
+    fn fetch(&self, n: usize) -> Result<Vec<u8>, CrngError> {
+        self.fetch_with_origin(n).map(|(bytes, _)| bytes)
+    }
+    
+    fn is_quantum(&self) -> bool {
+        true
+    }
+    
+    fn origin(&self) -> EntropyOrigin {
+        EntropyOrigin::Mixed
+    }
+    
+    fn fetch_with_origin(&self, n: usize) -> Result<(Vec<u8>, EntropyOrigin), CrngError> {
+        let (tx, rx) = mpsc::channel();
+        
+        for network in &self.sources {
+            let (source, client) = (network.source, network.client.clone());
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let result = source.fetch(&client, n, fetch_options());
+                let _ = tx.send((source, result));
+            });
+        }
+        drop(tx);
+        
+        // Take the first success; slower fetchers are left to finish in the background and ignored
+        for (source, result) in rx {
+            match result {
+                Ok(bytes) => {
+                    status!("🏁 {} won the race", source.name());
+                    return Ok((bytes, source.into()));
+                }
+                Err(e) => tracing::warn!(source = source.name(), error = %e, "❌ {}", source.name()),
+            }
+        }
+        
+        Err(CrngError::AllSourcesFailed)
+    }
+}
+
+/// Fetches from every provider concurrently and XORs the successful responses,
+/// so no single provider has to be trusted
+pub struct Mix {
+    pub sources: Vec<Network>,
+}
+
+impl EntropySource for Mix {
+    fn name(&self) -> &str {
+        "XOR mix of quantum sources"
+    }
+    
+    fn fetch(&self, n: usize) -> Result<Vec<u8>, CrngError> {
+        let handles: Vec<_> = self
+            .sources
+            .iter()
+            .map(|network| {
+                let (source, client) = (network.source, network.client.clone());
+                thread::spawn(move || (source, source.fetch(&client, n, fetch_options())))
+            })
+            .collect();
+        
+        let mut buffers = Vec::new();
+        for handle in handles {
+            match handle.join() {
+                Ok((source, Ok(bytes))) => {
+                    status!("✅ {}: Received {}", source.name(), green(format!("{} bytes", bytes.len())));
+                    buffers.push(bytes);
+                }
+                Ok((source, Err(e))) => tracing::warn!(source = source.name(), error = %e, "❌ {}", source.name()),
+                Err(_) => tracing::error!("❌ Source fetch thread panicked"),
+            }
+        }
+        
+        if buffers.is_empty() {
+            return Err(CrngError::AllSourcesFailed);
+        }
+        status!("🔀 Mixed {} sources", buffers.len());
+        Ok(xor_mix(&buffers, n))
+    }
+    
+    fn is_quantum(&self) -> bool {
+        true
+    }
+    
+    fn origin(&self) -> EntropyOrigin {
+        EntropyOrigin::Mixed
+    }
+}
+
+/// Quantum entropy saved by an earlier run
+pub struct SavedFile {
+    /// What to do when the saved bytes were already used
+    pub reuse: SavedReuse,
+    /// Oldest saved file accepted, by modification time
+    pub max_age: Option<Duration>,
+    /// Encoding the saved file is written in
+    pub encoding: Encoding,
+}
+
+impl EntropySource for SavedFile {
+    fn name(&self) -> &str {
+        "saved quantum entropy"
+    }
+    
+    fn fetch(&self, _n: usize) -> Result<Vec<u8>, CrngError> {
+        if let Some(max_age) = self.max_age {
+            let modified = std::fs::metadata(default_output_path())?.modified()?;
+            let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+            if age > max_age {
+                return Err(CrngError::TooOld { age: age.as_secs(), max_age: max_age.as_secs() });
+            }
+        }
+        
+        let bytes = load_saved_quantum_bytes(self.encoding)?;
+        if saved_entropy_was_used(&bytes) {
+            match self.reuse {
+                SavedReuse::Refuse => return Err(CrngError::AlreadyUsed),
+                SavedReuse::Warn => status!(
+                    "{}",
+                    yellow("⚠️  Warning: Saved entropy was already used by an earlier run, so it is not fresh")
+                ),
+                SavedReuse::Allow => {}
+            }
+        }
+        mark_saved_entropy_used(&bytes);
+        status!("♻️  Reusing saved quantum entropy from file: {}", cyan(default_output_path().display()));
+        Ok(bytes)
+    }
+    
+    fn is_quantum(&self) -> bool {
+        true
+    }
+    
+    fn origin(&self) -> EntropyOrigin {
+        EntropyOrigin::Saved
+    }
+}
+
+/// The operating system's CSRNG, the last resort when no quantum entropy is available
+pub struct Csrng;
+
+impl EntropySource for Csrng {
+    fn name(&self) -> &str {
+        "CSRNG"
+    }
+    
+    fn fetch(&self, n: usize) -> Result<Vec<u8>, CrngError> {
+        fetch_crypto_srng_bytes(n)
+    }
+    
+    fn is_quantum(&self) -> bool {
+        false
+    }
+    
+    fn origin(&self) -> EntropyOrigin {
+        EntropyOrigin::Csrng
+    }
+}
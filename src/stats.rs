@@ -0,0 +1,89 @@
+//! Per-source success statistics persisted across runs
+//!
+//! Attempts are recorded in memory while fetching and merged into a JSON file
+//! once the fetch finishes, so concurrent fetchers never write the file.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SourceStats {
+    pub attempts: u64,
+    pub successes: u64,
+    /// Total latency of successful attempts, in milliseconds
+    pub success_latency_ms: u64,
+}
+
+impl SourceStats {
+    /// Average latency of successful attempts
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.successes > 0).then(|| Duration::from_millis(self.success_latency_ms / self.successes))
+    }
+    
+    /// Fraction of attempts that succeeded, from 0.0 to 1.0
+    pub fn success_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.successes as f64 / self.attempts as f64
+        }
+    }
+    
+    /// Orders sources best first: higher success rate, then lower average latency
+    pub fn rank_cmp(&self, other: &SourceStats) -> Ordering {
+        other
+            .success_rate()
+            .total_cmp(&self.success_rate())
+            .then_with(|| self.average_latency().unwrap_or(Duration::MAX).cmp(&other.average_latency().unwrap_or(Duration::MAX)))
+    }
+}
+
+/// Attempts recorded since the last `persist`, keyed by source id
+static PENDING: Mutex<BTreeMap<String, SourceStats>> = Mutex::new(BTreeMap::new());
+
+/// Records one fetch attempt for `source`
+pub fn record(source: &str, success: bool, latency: Duration) {
+    let mut pending = PENDING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let stats = pending.entry(source.to_string()).or_default();
+    stats.attempts += 1;
+    if success {
+        stats.successes += 1;
+        stats.success_latency_ms += latency.as_millis() as u64;
+    }
+}
+
+/// Reads the statistics file, returning empty statistics if it doesn't exist
+pub fn load(path: &Path) -> Result<BTreeMap<String, SourceStats>, Box<dyn std::error::Error>> {
+    match fs::read(path) {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Merges the pending attempts into the statistics file at `path`
+pub fn persist(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    if pending.is_empty() {
+        return Ok(());
+    }
+    
+    let mut stats = load(path)?;
+    for (source, new) in pending {
+        let total = stats.entry(source).or_default();
+        total.attempts += new.attempts;
+        total.successes += new.successes;
+        total.success_latency_ms += new.success_latency_ms;
+    }
+    
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&stats)?)?;
+    Ok(())
+}
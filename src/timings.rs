@@ -0,0 +1,73 @@
+//! Per-phase timing instrumentation for `--timings`
+//!
+//! Phases running inside the parallel flip loop are summed across threads, so
+//! they measure CPU time rather than wall-clock time.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::helpers;
+
+#[derive(Clone, Copy)]
+pub enum Phase {
+    Fetch,
+    Csrng,
+    Counting,
+}
+
+impl Phase {
+    const ALL: [Phase; 3] = [Phase::Fetch, Phase::Csrng, Phase::Counting];
+    
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Fetch => "Network fetch",
+            Phase::Csrng => "CSRNG generation",
+            Phase::Counting => "Bit counting",
+        }
+    }
+}
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static PRINTING: AtomicBool = AtomicBool::new(false);
+static NANOS: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Turns on recording, and with `print` also the table printed by `print_table`
+pub fn set_enabled(record: bool, print: bool) {
+    RECORDING.store(record || print, Ordering::Relaxed);
+    PRINTING.store(print, Ordering::Relaxed);
+}
+
+/// Runs `f`, adding its duration to `phase` when timings are recorded
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return f();
+    }
+    
+    let start = Instant::now();
+    let result = f();
+    NANOS[phase as usize].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    result
+}
+
+/// Returns the time recorded for `phase` so far
+pub fn total(phase: Phase) -> Duration {
+    Duration::from_nanos(NANOS[phase as usize].load(Ordering::Relaxed))
+}
+
+/// Prints the recorded phase durations as a table, if timings are enabled
+pub fn print_table() {
+    if !PRINTING.load(Ordering::Relaxed) {
+        return;
+    }
+    
+    status!();
+    status!("⏱️  {}", helpers::bold("Timings"));
+    for phase in Phase::ALL {
+        let elapsed = total(phase);
+        let note = match phase {
+            Phase::Fetch => "",
+            Phase::Csrng | Phase::Counting => " (summed across threads)",
+        };
+        status!("   {:<18} {:>10.3} ms{}", phase.label(), elapsed.as_secs_f64() * 1000.0, note);
+    }
+}
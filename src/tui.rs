@@ -0,0 +1,140 @@
+//! Interactive terminal UI for live coin flipping (`qcoin tui`)
+
+use qcoin::CoinFlipper;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Sparkline, SparklineBar};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::time::Duration;
+
+/// How many recent outcomes the sparkline can show
+const HISTORY_LEN: usize = 512;
+/// Delay between flips in continuous mode
+const CONTINUOUS_INTERVAL: Duration = Duration::from_millis(50);
+
+struct App {
+    flipper: CoinFlipper,
+    source: String,
+    heads: u64,
+    tails: u64,
+    history: Vec<bool>,
+    continuous: bool,
+}
+
+impl App {
+    fn flip(&mut self) {
+        // The flipper is endless, so this never falls back
+        let heads = self.flipper.next().unwrap_or_default();
+        if heads {
+            self.heads += 1;
+        } else {
+            self.tails += 1;
+        }
+        
+        self.history.push(heads);
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+    }
+    
+    fn reset(&mut self) {
+        self.heads = 0;
+        self.tails = 0;
+        self.history.clear();
+    }
+}
+
+/// Runs the TUI until the user quits, drawing flips lazily from `flipper`
+pub fn run(flipper: CoinFlipper, source: &str) -> io::Result<()> {
+    let mut app = App {
+        flipper,
+        source: source.to_string(),
+        heads: 0,
+        tails: 0,
+        history: Vec::new(),
+        continuous: false,
+    };
+    
+    ratatui::run(|terminal| event_loop(terminal, &mut app))
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        
+        let timeout = if app.continuous { CONTINUOUS_INTERVAL } else { Duration::from_secs(1) };
+        if !event::poll(timeout)? {
+            if app.continuous {
+                app.flip();
+            }
+            continue;
+        }
+        
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match key.code {
+                KeyCode::Char(' ') | KeyCode::Enter => app.flip(),
+                KeyCode::Char('c') => app.continuous = !app.continuous,
+                KeyCode::Char('r') => app.reset(),
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [header, last, tally, sparkline, help] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    
+    let title = Paragraph::new(format!("Entropy source: {}", app.source)).block(Block::bordered().title(" 🎲 Quantum Coin Toss "));
+    frame.render_widget(title, header);
+    
+    let last_flip = match app.history.last() {
+        Some(true) => Line::from("HEADS").bold().fg(Color::Green),
+        Some(false) => Line::from("TAILS").bold().fg(Color::Red),
+        None => Line::from("Press space to flip"),
+    };
+    frame.render_widget(Paragraph::new(last_flip.centered()).block(Block::bordered().title(" Last flip ")), last);
+    
+    let total = app.heads + app.tails;
+    let percent = |count: u64| if total == 0 { 0.0 } else { count as f64 * 100.0 / total as f64 };
+    let tally_text = format!(
+        "Heads: {} ({:.1}%)   Tails: {} ({:.1}%)   Total: {}",
+        app.heads,
+        percent(app.heads),
+        app.tails,
+        percent(app.tails),
+        total
+    );
+    frame.render_widget(Paragraph::new(tally_text).block(Block::bordered().title(" Tally ")), tally);
+    
+    // Heads are drawn as full bars and tails as half bars, newest on the right
+    let width = sparkline.width.saturating_sub(2) as usize;
+    let recent = &app.history[app.history.len().saturating_sub(width)..];
+    let bars = recent.iter().map(|&heads| {
+        if heads {
+            SparklineBar::from(2).style(Some(Style::default().fg(Color::Green)))
+        } else {
+            SparklineBar::from(1).style(Some(Style::default().fg(Color::Red)))
+        }
+    });
+    let chart = Sparkline::default().data(bars).max(2).block(Block::bordered().title(" Recent flips "));
+    frame.render_widget(chart, sparkline);
+    
+    let mode = if app.continuous { "on" } else { "off" };
+    frame.render_widget(
+        Paragraph::new(format!("space: flip   c: continuous ({})   r: reset   q: quit", mode)).dim(),
+        help,
+    );
+}
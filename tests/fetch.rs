@@ -0,0 +1,521 @@
+//! Runs the entropy fetchers against local mock servers, reached through the
+//! --anu-url and --qrandom-url overrides or the library's `FetchOptions` URLs
+
+use httpmock::prelude::*;
+use serde_json::json;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const ENTROPY: [u8; 16] = [0, 1, 2, 3, 250, 251, 252, 253, 17, 34, 51, 68, 85, 102, 119, 136];
+
+/// Creates an empty home directory so no config, saved entropy or statistics leak in
+fn fresh_home() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let home = std::env::temp_dir().join(format!("qcoin-fetch-test-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+    let _ = fs::remove_dir_all(&home);
+    fs::create_dir_all(&home).unwrap();
+    home
+}
+
+/// Fetches 16 bytes as hex from `source` alone, with no saved entropy or CSRNG to fall back on
+fn fetch_hex(source: &str, server: &MockServer) -> Output {
+    fetch_hex_with(source, server, &[])
+}
+
+/// Like `fetch_hex`, with `extra` command line arguments
+fn fetch_hex_with(source: &str, server: &MockServer, extra: &[&str]) -> Output {
+    let mut args = vec!["bytes", "-n", "16", "--format", "raw-hex"];
+    args.extend(extra);
+    run_qcoin(source, server, &args)
+}
+
+/// Runs qcoin with `args`, fetching from `source` alone with no CSRNG to fall back on
+fn run_qcoin(source: &str, server: &MockServer, args: &[&str]) -> Output {
+    let home = fresh_home();
+    let output = qcoin(source, server, &home).args(args).output().unwrap();
+    let _ = fs::remove_dir_all(&home);
+    output
+}
+
+/// Builds a qcoin command living in `home` that fetches from `source` alone
+fn qcoin(source: &str, server: &MockServer, home: &Path) -> Command {
+    let config = home.join("config.toml");
+    fs::write(&config, format!("sources = [\"{}\"]\n", source)).unwrap();
+    
+    let mut command = Command::new(env!("CARGO_BIN_EXE_qcoin"));
+    command
+        .args(["--quantum-only", "--no-color"])
+        .arg("--config")
+        .arg(&config)
+        .args(["--anu-url", &server.base_url(), "--qrandom-url", &server.base_url()])
+        .env_remove("ANU_API_KEY")
+        .env("HOME", home)
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("XDG_DATA_HOME", home.join("data"));
+    command
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn anu_uint8_response_is_returned_as_is() {
+    let server = MockServer::start();
+    let anu = server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php").query_param("length", "16").query_param("type", "uint8");
+        then.status(200).json_body(json!({ "type": "uint8", "length": 16, "data": ENTROPY, "success": true }));
+    });
+    
+    let output = fetch_hex("anu", &server);
+    anu.assert();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), hex::encode(ENTROPY));
+}
+
+#[test]
+fn anu_http_error_fails_the_source() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(500).body("internal error");
+    });
+    
+    let output = fetch_hex("anu", &server);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("ANU QRNG: HTTP 500"), "{}", stderr(&output));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn anu_api_key_switches_to_the_keyed_api() {
+    let server = MockServer::start();
+    let keyed = server.mock(|when, then| {
+        when.method(GET).path("/").query_param("length", "16").query_param("type", "uint8").header("x-api-key", "test-key");
+        then.status(200).json_body(json!({ "type": "uint8", "length": "16", "data": ENTROPY, "success": true }));
+    });
+    let legacy = server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(500);
+    });
+    
+    let output = fetch_hex_with("anu", &server, &["--anu-api-key", "test-key", "--anu-api-url", &server.base_url()]);
+    keyed.assert();
+    legacy.assert_calls(0);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), hex::encode(ENTROPY));
+}
+
+#[test]
+fn anu_api_key_prefers_the_flag_then_the_environment_then_the_config() {
+    let server = MockServer::start();
+    let keyed = |key: &'static str| {
+        server.mock(move |when, then| {
+            when.method(GET).path("/").header("x-api-key", key);
+            then.status(200).json_body(json!({ "type": "uint8", "length": "16", "data": ENTROPY, "success": true }));
+        })
+    };
+    let (flag, env, config) = (keyed("flag-key"), keyed("env-key"), keyed("config-key"));
+    
+    let fetch = |extra: &[&str], env_key: Option<&str>| {
+        let home = fresh_home();
+        let mut command = qcoin("anu", &server, &home);
+        let mut file = fs::OpenOptions::new().append(true).open(home.join("config.toml")).unwrap();
+        writeln!(file, "anu_api_key = \"config-key\"").unwrap();
+        command.args(["bytes", "-n", "16", "--format", "raw-hex", "--anu-api-url", &server.base_url()]).args(extra);
+        if let Some(key) = env_key {
+            command.env("ANU_API_KEY", key);
+        }
+        let output = command.output().unwrap();
+        let _ = fs::remove_dir_all(&home);
+        assert!(output.status.success(), "{}", stderr(&output));
+    };
+    
+    fetch(&[], None);
+    config.assert_calls(1);
+    fetch(&[], Some("env-key"));
+    env.assert_calls(1);
+    fetch(&["--anu-api-key", "flag-key"], Some("env-key"));
+    flag.assert_calls(1);
+    assert_eq!((config.calls(), env.calls()), (1, 1));
+}
+
+#[test]
+fn json_log_carries_the_fetch_span() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(500);
+    });
+    
+    let output = fetch_hex_with("anu", &server, &["--log-format", "json"]);
+    assert!(!output.status.success());
+    let event = stderr(&output)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .find(|event| event["level"] == "WARN")
+        .unwrap_or_else(|| panic!("no JSON warning in {}", stderr(&output)));
+    assert_eq!(event["fields"]["error"], "HTTP 500 Internal Server Error");
+    assert_eq!(event["fields"]["message"], "❌ ANU QRNG");
+    assert_eq!(event["span"]["name"], "fetch");
+    assert_eq!(event["span"]["source"], "ANU QRNG");
+    assert_eq!(event["span"]["bytes"], 16);
+}
+
+#[test]
+fn pretty_log_appends_the_error_field() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(500);
+    });
+    
+    let output = fetch_hex("anu", &server);
+    assert!(!output.status.success());
+    assert!(stderr(&output).lines().any(|line| line == "❌ ANU QRNG: HTTP 500 Internal Server Error"), "{}", stderr(&output));
+}
+
+#[test]
+fn status_lines_are_info_events_on_stdout() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php").query_param("length", "16").query_param("type", "uint8");
+        then.status(200).json_body(json!({ "type": "uint8", "length": 16, "data": ENTROPY, "success": true }));
+    });
+    let flip = |extra: &[&str], rust_log: Option<&str>| {
+        let home = fresh_home();
+        let mut command = qcoin("anu", &server, &home);
+        command.args(["-n", "3", "--entropy-bytes", "16", "--no-save"]).args(extra).env_remove("RUST_LOG");
+        if let Some(directives) = rust_log {
+            command.env("RUST_LOG", directives);
+        }
+        let output = command.output().unwrap();
+        let _ = fs::remove_dir_all(&home);
+        assert!(output.status.success(), "{}", stderr(&output));
+        (String::from_utf8_lossy(&output.stdout).into_owned(), stderr(&output))
+    };
+    
+    let (stdout, _) = flip(&[], None);
+    assert!(stdout.lines().any(|line| line == "📊 Flips: 3"), "{}", stdout);
+    
+    let (stdout, _) = flip(&[], Some("warn"));
+    assert!(!stdout.contains("Flips"), "{}", stdout);
+    assert!(stdout.contains("Outcome"), "{}", stdout);
+    
+    let (stdout, stderr) = flip(&["--log-format", "json"], None);
+    assert!(!stdout.contains("Flips"), "{}", stdout);
+    assert!(
+        stderr
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .any(|event| event["target"] == "qcoin::status" && event["level"] == "INFO" && event["fields"]["message"] == "📊 Flips: 3"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn json_format_reports_failures_as_json() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(500);
+    });
+    
+    let output = run_qcoin("anu", &server, &["-n", "3", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(error["error"], "All quantum sources failed and --quantum-only forbids the CSRNG fallback");
+    assert_eq!(error["code"], 1);
+}
+
+#[test]
+fn dedupe_flags_a_provider_serving_the_same_bytes_twice() {
+    let server = MockServer::start();
+    let anu = server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(200).json_body(json!({ "type": "uint8", "length": 16, "data": ENTROPY, "success": true }));
+    });
+    
+    let args = ["-n", "3", "--entropy-bytes", "16", "--repeat", "2", "--fresh-entropy", "--format", "json"];
+    let warned = run_qcoin("anu", &server, &[&args[..], &["--dedupe"]].concat());
+    assert!(warned.status.success(), "{}", stderr(&warned));
+    assert!(stderr(&warned).contains("ANU QRNG served the same 16 bytes as a recent fetch"), "{}", stderr(&warned));
+    assert_eq!(anu.calls(), 2);
+    
+    let refetched = run_qcoin("anu", &server, &[&args[..], &["--dedupe", "refetch"]].concat());
+    assert!(!refetched.status.success());
+    assert!(stderr(&refetched).contains("ANU QRNG: Served the same bytes as a recent fetch"), "{}", stderr(&refetched));
+    assert_eq!(anu.calls(), 5);
+}
+
+#[test]
+fn stream_writes_quantum_chunks_until_the_reader_leaves() {
+    let server = MockServer::start();
+    // The pool asks for whatever it lacks of its 64 bytes, in multiples of the 16-byte chunk
+    for chunks in 1..=4 {
+        let data = [ENTROPY; 4].concat()[..16 * chunks].to_vec();
+        server.mock(|when, then| {
+            when.method(GET).path("/API/jsonI.php").query_param("length", (16 * chunks).to_string());
+            then.status(200).json_body(json!({ "type": "uint8", "length": data.len(), "data": data, "success": true }));
+        });
+    }
+    
+    let home = fresh_home();
+    let mut child = qcoin("anu", &server, &home)
+        .args(["--entropy-bytes", "16", "--no-delay", "stream", "--stretch", "0"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut streamed = [0u8; 48];
+    child.stdout.take().unwrap().read_exact(&mut streamed).unwrap();
+    
+    // Dropping stdout closes the pipe, which ends the stream
+    let output = child.wait_with_output().unwrap();
+    let _ = fs::remove_dir_all(&home);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(streamed, [ENTROPY; 3].concat()[..]);
+    assert!(stderr(&output).contains("🚰 Streamed"), "{}", stderr(&output));
+}
+
+#[test]
+fn chunked_anu_fetch_takes_one_rate_limit_token() {
+    let server = MockServer::start();
+    let hex16 = server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php").query_param("type", "hex16");
+        then.status(500);
+    });
+    let chunks = server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php").query_param("length", "16").query_param("type", "uint8");
+        then.status(200).json_body(json!({ "type": "uint8", "length": 16, "data": ENTROPY, "success": true }));
+    });
+    
+    // At one fetch per minute, charging each of the 4 requests would take 3 minutes
+    let start = std::time::Instant::now();
+    let output = run_qcoin("anu", &server, &["bytes", "-n", "48", "--format", "raw-hex", "--chunk-size", "16", "--rate-limit", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(start.elapsed() < std::time::Duration::from_secs(20), "took {:?}", start.elapsed());
+    hex16.assert_calls(1);
+    chunks.assert_calls(3);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), hex::encode(ENTROPY).repeat(3));
+}
+
+#[test]
+fn anu_short_response_is_rejected() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(200).json_body(json!({ "type": "uint8", "length": 4, "data": [1, 2, 3, 4], "success": true }));
+    });
+    
+    let output = fetch_hex("anu", &server);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Expected 16 bytes, got 4"), "{}", stderr(&output));
+}
+
+#[test]
+fn anu_malformed_json_is_rejected() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(200).header("content-type", "application/json").body("{\"data\": [1, 2,");
+    });
+    
+    let output = fetch_hex("anu", &server);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("❌ ANU QRNG:"), "{}", stderr(&output));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn qrandom_hex_endpoint_is_decoded() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/hex").query_param("bytes", "16");
+        then.status(200).json_body(json!({ "hex": hex::encode(ENTROPY), "id": "mock" }));
+    });
+    
+    let output = fetch_hex("qrandom", &server);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), hex::encode(ENTROPY));
+}
+
+#[test]
+fn qrandom_binary_flow_follows_the_binary_url() {
+    let server = MockServer::start();
+    let hex = server.mock(|when, then| {
+        when.method(GET).path("/api/random/hex");
+        then.status(404);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/binary").query_param("bytes", "16");
+        then.status(200).json_body(json!({ "binaryURL": server.url("/download/mock.bin") }));
+    });
+    let download = server.mock(|when, then| {
+        when.method(GET).path("/download/mock.bin");
+        then.status(200).body(ENTROPY);
+    });
+    
+    let output = fetch_hex("qrandom", &server);
+    hex.assert();
+    download.assert();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), hex::encode(ENTROPY));
+}
+
+#[test]
+fn qrandom_binary_url_to_another_host_is_refused() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/hex");
+        then.status(404);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/binary");
+        then.status(200).json_body(json!({ "binaryURL": "http://attacker.invalid/mock.bin" }));
+    });
+    
+    let output = fetch_hex("qrandom", &server);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Refusing to follow binary URL"), "{}", stderr(&output));
+}
+
+#[test]
+fn qrandom_short_response_is_rejected() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/hex");
+        then.status(200).json_body(json!({ "hex": "abcd" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/binary");
+        then.status(503);
+    });
+    
+    let output = fetch_hex("qrandom", &server);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Expected 16 bytes, got 2"), "{}", stderr(&output));
+}
+
+fn library_options(server: &MockServer, sources: Vec<qcoin::fetch::Source>) -> qcoin::FetchOptions {
+    qcoin::FetchOptions {
+        sources,
+        anu_url: server.base_url(),
+        qrandom_url: server.base_url(),
+        csrng_fallback: false,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn library_fetch_returns_bytes_with_their_source() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php").query_param("length", "16").query_param("type", "uint8");
+        then.status(200).json_body(json!({ "type": "uint8", "length": 16, "data": ENTROPY, "success": true }));
+    });
+    
+    let entropy = qcoin::fetch_entropy(16, &library_options(&server, vec![qcoin::fetch::Source::Anu])).unwrap();
+    assert_eq!(entropy.bytes, ENTROPY);
+    assert_eq!(entropy.source, "anu");
+    assert!(entropy.is_quantum);
+}
+
+#[test]
+fn library_fetch_sends_the_anu_api_key() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/").query_param("length", "16").header("x-api-key", "test-key");
+        then.status(200).json_body(json!({ "type": "uint8", "length": "16", "data": ENTROPY, "success": true }));
+    });
+    
+    let options = qcoin::FetchOptions {
+        anu_api_key: Some("test-key".to_string()),
+        anu_api_url: server.base_url(),
+        ..library_options(&server, vec![qcoin::fetch::Source::Anu])
+    };
+    assert_eq!(qcoin::fetch_entropy(16, &options).unwrap().bytes, ENTROPY);
+}
+
+#[test]
+fn library_fetch_falls_through_to_the_next_source() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(500);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/hex").query_param("bytes", "16");
+        then.status(200).json_body(json!({ "hex": hex::encode(ENTROPY) }));
+    });
+    
+    let options = library_options(&server, vec![qcoin::fetch::Source::Anu, qcoin::fetch::Source::Qrandom]);
+    let entropy = qcoin::fetch_entropy(16, &options).unwrap();
+    assert_eq!(entropy.bytes, ENTROPY);
+    assert_eq!(entropy.source, "qrandom");
+}
+
+#[test]
+fn anu_chunks_are_fetched_at_most_three_at_a_time() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php").query_param("type", "hex16");
+        then.status(500);
+    });
+    let chunks = server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php").query_param("length", "16").query_param("type", "uint8");
+        then.status(200)
+            .delay(std::time::Duration::from_millis(400))
+            .json_body(json!({ "type": "uint8", "length": 16, "data": ENTROPY, "success": true }));
+    });
+    
+    // Six chunks take two rounds of the delay: one round would mean no limit, six no overlap
+    let options = qcoin::FetchOptions { anu_chunk_size: 16, ..library_options(&server, vec![qcoin::fetch::Source::Anu]) };
+    let start = std::time::Instant::now();
+    let entropy = qcoin::fetch_entropy(96, &options).unwrap();
+    let elapsed = start.elapsed();
+    chunks.assert_calls(6);
+    assert_eq!(entropy.bytes, ENTROPY.repeat(6));
+    assert!(elapsed >= std::time::Duration::from_millis(800), "took {:?}", elapsed);
+    assert!(elapsed < std::time::Duration::from_millis(2000), "took {:?}", elapsed);
+}
+
+#[test]
+fn library_fetch_shares_the_binary_qrandom_flow() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/hex");
+        then.status(404);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/api/random/binary");
+        then.status(200).json_body(json!({ "binaryURL": "http://attacker.invalid/mock.bin" }));
+    });
+    
+    let options = library_options(&server, vec![qcoin::fetch::Source::Qrandom]);
+    let client = qcoin::fetch::client(&options).unwrap();
+    let err = qcoin::fetch::fetch_from(&client, qcoin::fetch::Source::Qrandom, 16, &options).unwrap_err();
+    assert!(err.to_string().contains("Refusing to follow binary URL"), "{}", err);
+}
+
+#[test]
+fn library_fetch_uses_the_csrng_only_when_allowed() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/API/jsonI.php");
+        then.status(503);
+    });
+    
+    let mut options = library_options(&server, vec![qcoin::fetch::Source::Anu]);
+    assert!(matches!(qcoin::fetch_entropy(16, &options), Err(qcoin::CrngError::AllSourcesFailed)));
+    
+    options.csrng_fallback = true;
+    let entropy = qcoin::fetch_entropy(16, &options).unwrap();
+    assert_eq!(entropy.bytes.len(), 16);
+    assert_eq!(entropy.source, "csrng");
+    assert!(!entropy.is_quantum);
+}